@@ -0,0 +1,144 @@
+use std::path::{Path, PathBuf};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Ident, LitStr, Token, parse::Parser};
+
+struct BundleArgs {
+    path: LitStr,
+    check: bool,
+}
+
+fn parse_args(input: TokenStream) -> BundleArgs {
+    let parser = |input: syn::parse::ParseStream| {
+        let path: LitStr = input.parse()?;
+        let mut check = false;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let flag: Ident = input.parse()?;
+            if flag != "check" {
+                return Err(syn::Error::new(flag.span(), "expected `check`"));
+            }
+            check = true;
+        }
+        Ok(BundleArgs { path, check })
+    };
+    parser
+        .parse2(input)
+        .unwrap_or_else(|e| panic!("invalid include_lua! arguments: {}", e))
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = std::fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("cannot read directory {}: {}", dir.display(), e));
+    for entry in entries {
+        let path = entry
+            .unwrap_or_else(|e| panic!("cannot read directory {}: {}", dir.display(), e))
+            .path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "lua") {
+            files.push(path);
+        }
+    }
+}
+
+fn module_name(relative: &Path) -> String {
+    let mut parts: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if parts.len() > 1 && parts.last().is_some_and(|p| p == "init") {
+        parts.pop();
+    }
+    parts.join(".")
+}
+
+#[cfg(feature = "check")]
+fn check_syntax(chunk_name: &str, code: &[u8]) -> Result<(), String> {
+    use mlua_sys as sys;
+
+    let name = std::ffi::CString::new(format!("@{}", chunk_name)).map_err(|e| e.to_string())?;
+    unsafe {
+        let ptr = sys::luaL_newstate();
+        if ptr.is_null() {
+            return Err("failed to create lua state".into());
+        }
+        let status = sys::luaL_loadbuffer(ptr, code.as_ptr() as _, code.len(), name.as_ptr());
+        let result = if status == 0 {
+            Ok(())
+        } else {
+            let mut len = 0;
+            let msg = sys::lua_tolstring(ptr, -1, &mut len);
+            let msg = std::slice::from_raw_parts(msg as *const u8, len);
+            Err(String::from_utf8_lossy(msg).into_owned())
+        };
+        sys::lua_close(ptr);
+        result
+    }
+}
+
+pub fn include_lua(input: TokenStream) -> TokenStream {
+    let args = parse_args(input);
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let root = Path::new(&manifest_dir).join(args.path.value());
+
+    let mut files = vec![];
+    if args.check && !cfg!(feature = "check") {
+        panic!(
+            "include_lua!(\"{}\", check) requires the `check` feature",
+            args.path.value()
+        );
+    }
+
+    collect_files(&root, &mut files);
+    files.sort();
+
+    let entries = files.iter().map(|file| {
+        let relative = file.strip_prefix(&root).unwrap();
+        let module = module_name(relative);
+        let chunk_name = Path::new(&args.path.value())
+            .join(relative)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        #[cfg(feature = "check")]
+        if args.check {
+            let code = std::fs::read(file)
+                .unwrap_or_else(|e| panic!("cannot read {}: {}", file.display(), e));
+            if let Err(msg) = check_syntax(&chunk_name, &code) {
+                panic!("syntax error in {}", msg);
+            }
+        }
+
+        let absolute = file.to_string_lossy();
+        quote! {
+            ljr::lua::BundleFile::new(#module, #chunk_name, include_bytes!(#absolute))
+        }
+    });
+
+    quote! {
+        ljr::lua::Bundle::new(&[#(#entries),*])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_name() {
+        assert_eq!(module_name(Path::new("main.lua")), "main");
+        assert_eq!(module_name(Path::new("ui/button.lua")), "ui.button");
+        assert_eq!(module_name(Path::new("ui/init.lua")), "ui");
+        assert_eq!(module_name(Path::new("init.lua")), "init");
+    }
+
+    #[test]
+    #[cfg(feature = "check")]
+    fn test_check_syntax() {
+        assert!(check_syntax("ok.lua", b"return 1").is_ok());
+        let err = check_syntax("bad.lua", b"return +").unwrap_err();
+        assert!(err.starts_with("bad.lua:1:"));
+    }
+}
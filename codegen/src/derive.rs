@@ -0,0 +1,414 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Meta, Token, parse::Parser, punctuated::Punctuated};
+use venial::{Fields, Item};
+
+#[derive(Default)]
+struct LuaAttrs {
+    rename: Option<String>,
+    default: Option<TokenStream>,
+    skip: bool,
+    tag: Option<String>,
+}
+
+fn lit_str_value(expr: &syn::Expr, name: &str) -> String {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit),
+            ..
+        }) => lit.value(),
+        _ => panic!("lua attribute {} expects a string literal", name),
+    }
+}
+
+fn parse_lua_attrs(attrs: &[venial::Attribute]) -> LuaAttrs {
+    let mut result = LuaAttrs::default();
+    for attr in attrs {
+        if !matches!(attr.get_single_path_segment(), Some(ident) if ident == "lua") {
+            continue;
+        }
+
+        let tokens: TokenStream = match &attr.value {
+            venial::AttributeValue::Group(_, tokens) => tokens.iter().cloned().collect(),
+            _ => panic!("expected #[lua(...)]"),
+        };
+        let metas = Punctuated::<Meta, Token![,]>::parse_terminated
+            .parse2(tokens)
+            .unwrap_or_else(|e| panic!("invalid lua attribute: {}", e));
+
+        for meta in metas {
+            let name = meta
+                .path()
+                .get_ident()
+                .map(|i| i.to_string())
+                .unwrap_or_default();
+            match (name.as_str(), &meta) {
+                ("skip", Meta::Path(_)) => result.skip = true,
+                ("default", Meta::Path(_)) => {
+                    result.default = Some(quote! { ::core::default::Default::default })
+                }
+                ("default", Meta::NameValue(nv)) => {
+                    let path: syn::Path = syn::parse_str(&lit_str_value(&nv.value, "default"))
+                        .unwrap_or_else(|e| panic!("invalid default function path: {}", e));
+                    result.default = Some(quote! { #path });
+                }
+                ("rename", Meta::NameValue(nv)) => {
+                    result.rename = Some(lit_str_value(&nv.value, "rename"))
+                }
+                ("tag", Meta::NameValue(nv)) => result.tag = Some(lit_str_value(&nv.value, "tag")),
+                _ => panic!("unknown lua attribute {}", name),
+            }
+        }
+    }
+    result
+}
+
+enum Key {
+    Name(String),
+    Index(i32),
+}
+
+struct Field {
+    binding: syn::Ident,
+    member: TokenStream,
+    key: Key,
+    ty: TokenStream,
+    attrs: LuaAttrs,
+}
+
+fn collect_fields(fields: &Fields) -> Vec<Field> {
+    match fields {
+        Fields::Unit => vec![],
+        Fields::Named(named) => named
+            .fields
+            .iter()
+            .map(|(f, _)| {
+                let attrs = parse_lua_attrs(&f.attributes);
+                let name = &f.name;
+                let ty = &f.ty;
+                Field {
+                    binding: format_ident!("__{}", name),
+                    member: quote! { #name },
+                    key: Key::Name(attrs.rename.clone().unwrap_or_else(|| name.to_string())),
+                    ty: quote! { #ty },
+                    attrs,
+                }
+            })
+            .collect(),
+        Fields::Tuple(tuple) => {
+            let mut index = 0;
+            tuple
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, (f, _))| {
+                    let attrs = parse_lua_attrs(&f.attributes);
+                    if attrs.rename.is_some() {
+                        panic!("tuple fields cannot be renamed");
+                    }
+                    if !attrs.skip {
+                        index += 1;
+                    }
+                    let member = syn::Index::from(i);
+                    let ty = &f.ty;
+                    Field {
+                        binding: format_ident!("__{}", i),
+                        member: quote! { #member },
+                        key: Key::Index(index),
+                        ty: quote! { #ty },
+                        attrs,
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+fn destructure(path: TokenStream, fields: &Fields, list: &[Field]) -> TokenStream {
+    let bindings = list.iter().map(|f| {
+        let binding = &f.binding;
+        if f.attrs.skip {
+            quote! { _ }
+        } else {
+            quote! { #binding }
+        }
+    });
+    match fields {
+        Fields::Unit => path,
+        Fields::Tuple(_) => quote! { #path(#(#bindings),*) },
+        Fields::Named(_) => {
+            let members = list.iter().map(|f| &f.member);
+            quote! { #path { #(#members: #bindings),* } }
+        }
+    }
+}
+
+fn gen_push_table(tag: Option<(&str, String)>, list: &[Field]) -> TokenStream {
+    let fields = list.iter().filter(|f| !f.attrs.skip);
+    let narr = fields
+        .clone()
+        .filter(|f| matches!(f.key, Key::Index(_)))
+        .count() as i32;
+    let nrec = fields
+        .clone()
+        .filter(|f| matches!(f.key, Key::Name(_)))
+        .count() as i32
+        + tag.is_some() as i32;
+
+    let tag_set = tag.map(|(key, name)| {
+        quote! {
+            if let Err(e) = unsafe { ljr::helper::try_set_field(ptr, #key, #name) } {
+                unsafe { ljr::sys::lua_pop(ptr, 1) };
+                return Err(e);
+            }
+        }
+    });
+
+    let sets = fields.map(|f| {
+        let binding = &f.binding;
+        let set = match &f.key {
+            Key::Name(key) => quote! { ljr::helper::try_set_field(ptr, #key, #binding) },
+            Key::Index(index) => quote! { ljr::helper::try_set_index(ptr, #index, #binding) },
+        };
+        quote! {
+            if let Err(e) = unsafe { #set } {
+                unsafe { ljr::sys::lua_pop(ptr, 1) };
+                return Err(e);
+            }
+        }
+    });
+
+    quote! {
+        unsafe {
+            ljr::helper::try_check_stack(ptr, 1)?;
+            ljr::sys::lua_createtable(ptr, #narr, #nrec);
+        }
+        #tag_set
+        #(#sets)*
+        Ok(())
+    }
+}
+
+fn gen_read_table(path: TokenStream, fields: &Fields, list: &[Field]) -> TokenStream {
+    let values = list.iter().map(|f| {
+        let ty = &f.ty;
+        if f.attrs.skip {
+            return quote! { ::core::default::Default::default() };
+        }
+        let default = match &f.attrs.default {
+            Some(default) => quote! { Some(#default) },
+            None => quote! { None },
+        };
+        match &f.key {
+            Key::Name(key) => {
+                quote! { unsafe { ljr::helper::try_get_field::<#ty>(ptr, idx, #key, #default)? } }
+            }
+            Key::Index(index) => {
+                quote! { unsafe { ljr::helper::try_get_index::<#ty>(ptr, idx, #index, #default)? } }
+            }
+        }
+    });
+    match fields {
+        Fields::Unit => path,
+        Fields::Tuple(_) => quote! { #path(#(#values),*) },
+        Fields::Named(_) => {
+            let members = list.iter().map(|f| &f.member);
+            quote! { #path { #(#members: #values),* } }
+        }
+    }
+}
+
+struct Header {
+    name: syn::Ident,
+    params: TokenStream,
+    args: TokenStream,
+}
+
+fn header(item: &Item) -> Header {
+    let (name, params, args) = match item {
+        Item::Struct(s) => (&s.name, &s.generic_params, s.get_inline_generic_args()),
+        Item::Enum(e) => (&e.name, &e.generic_params, e.get_inline_generic_args()),
+        _ => panic!("ToLua and FromLua can only be derived for structs and enums"),
+    };
+    Header {
+        name: name.clone(),
+        params: quote! { #params },
+        args: quote! { #args },
+    }
+}
+
+fn where_clause(item: &Item, bound: TokenStream) -> TokenStream {
+    match item {
+        Item::Struct(s) => {
+            let clause = s.create_derive_where_clause(bound);
+            quote! { #clause }
+        }
+        Item::Enum(e) => {
+            let clause = e.create_derive_where_clause(bound);
+            quote! { #clause }
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn variant_name(variant: &venial::EnumVariant) -> String {
+    parse_lua_attrs(&variant.attributes)
+        .rename
+        .unwrap_or_else(|| variant.name.to_string())
+}
+
+fn enum_tag(e: &venial::Enum) -> String {
+    parse_lua_attrs(&e.attributes)
+        .tag
+        .unwrap_or_else(|| "type".to_string())
+}
+
+pub fn derive_to_lua(item: TokenStream) -> TokenStream {
+    let item = venial::parse_item(item).unwrap();
+    let Header { name, params, args } = header(&item);
+    let where_clause = where_clause(&item, quote! { ljr::to_lua::ToLua });
+
+    let body = match &item {
+        Item::Struct(s) => {
+            let list = collect_fields(&s.fields);
+            let pattern = destructure(quote! { Self }, &s.fields, &list);
+            let push = gen_push_table(None, &list);
+            quote! {
+                let #pattern = self;
+                #push
+            }
+        }
+        Item::Enum(e) => {
+            let tag = enum_tag(e);
+            let arms = e.variants.iter().map(|(variant, _)| {
+                let ident = &variant.name;
+                let lua_name = variant_name(variant);
+                let list = collect_fields(&variant.fields);
+                let pattern = destructure(quote! { Self::#ident }, &variant.fields, &list);
+                let push = match variant.fields {
+                    Fields::Unit => quote! { ljr::to_lua::ToLua::try_to_lua(#lua_name, ptr) },
+                    _ => {
+                        let push = gen_push_table(Some((&tag, lua_name)), &list);
+                        quote! {{ #push }}
+                    }
+                };
+                quote! { #pattern => #push, }
+            });
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        _ => unreachable!(),
+    };
+
+    quote! {
+        unsafe impl #params ljr::to_lua::ToLua for #name #args #where_clause {
+            unsafe fn try_to_lua_unchecked(
+                self,
+                ptr: *mut ljr::sys::lua_State,
+            ) -> Result<(), ljr::error::Error> {
+                #body
+            }
+        }
+    }
+}
+
+pub fn derive_from_lua(item: TokenStream) -> TokenStream {
+    let item = venial::parse_item(item).unwrap();
+    let Header { name, params, args } = header(&item);
+    let from_lua_where = where_clause(
+        &item,
+        quote! { ljr::from_lua::FromLua + ljr::lua::ValueArg },
+    );
+    let value_arg_where = where_clause(&item, quote! { ljr::lua::ValueArg });
+
+    let body = match &item {
+        Item::Struct(s) => {
+            let list = collect_fields(&s.fields);
+            let read = gen_read_table(quote! { Self }, &s.fields, &list);
+            quote! {
+                if unsafe { ljr::sys::lua_istable(ptr, idx) } == 0 {
+                    return Err(ljr::error::Error::UnexpectedType);
+                }
+                let idx = unsafe { ljr::sys::lua_absindex(ptr, idx) };
+                Ok(#read)
+            }
+        }
+        Item::Enum(e) => {
+            let tag = enum_tag(e);
+            let unit_arms = e.variants.iter().filter_map(|(variant, _)| {
+                let ident = &variant.name;
+                let lua_name = variant_name(variant);
+                match variant.fields {
+                    Fields::Unit => Some(quote! { #lua_name => Ok(Self::#ident), }),
+                    _ => None,
+                }
+            });
+            let table_arms = e.variants.iter().map(|(variant, _)| {
+                let ident = &variant.name;
+                let lua_name = variant_name(variant);
+                let list = collect_fields(&variant.fields);
+                let read = gen_read_table(quote! { Self::#ident }, &variant.fields, &list);
+                quote! { #lua_name => Ok(#read), }
+            });
+            quote! {
+                let idx = unsafe { ljr::sys::lua_absindex(ptr, idx) };
+                if unsafe { ljr::sys::lua_type(ptr, idx) } == ljr::sys::LUA_TSTRING as i32 {
+                    let name = <String as ljr::from_lua::FromLua>::try_from_lua(ptr, idx)?;
+                    return match name.as_str() {
+                        #(#unit_arms)*
+                        _ => Err(ljr::error::Error::UnknownVariant(name)),
+                    };
+                }
+                if unsafe { ljr::sys::lua_istable(ptr, idx) } == 0 {
+                    return Err(ljr::error::Error::UnexpectedType);
+                }
+                let name = unsafe { ljr::helper::try_get_field::<String>(ptr, idx, #tag, None)? };
+                match name.as_str() {
+                    #(#table_arms)*
+                    _ => Err(ljr::error::Error::UnknownVariant(name)),
+                }
+            }
+        }
+        _ => unreachable!(),
+    };
+
+    quote! {
+        unsafe impl #params ljr::from_lua::FromLua for #name #args #from_lua_where {
+            fn try_from_lua(
+                ptr: *mut ljr::sys::lua_State,
+                idx: i32,
+            ) -> Result<Self, ljr::error::Error> {
+                #body
+            }
+        }
+
+        unsafe impl #params ljr::lua::ValueArg for #name #args #value_arg_where {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generic_bounds() {
+        let output = derive_from_lua(quote! {
+            struct Wrapper<T> { value: T }
+        })
+        .to_string();
+
+        assert!(output.contains("T : ljr :: from_lua :: FromLua + ljr :: lua :: ValueArg"));
+        assert!(output.contains("ljr :: lua :: ValueArg for Wrapper <"));
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown lua attribute flatten")]
+    fn test_unknown_attr() {
+        derive_to_lua(quote! {
+            struct Test { #[lua(flatten)] value: i32 }
+        });
+    }
+}
@@ -1,5 +1,7 @@
 pub mod tuple_impl;
 pub mod module;
+pub mod derive;
+pub mod bundle;
 mod type_info;
 
 use proc_macro2::{Span, TokenStream, TokenTree};
@@ -28,6 +30,154 @@ macro_rules! try_or_return {
     };
 }
 
+const META_METHODS: [&str; 14] = ["tostring", "eq", "lt", "le", "add", "sub", "mul", "div", "mod", "pow", "unm", "concat", "len", "call"];
+
+const COMMUTATIVE_META_METHODS: [&str; 2] = ["__add", "__mul"];
+
+fn is_optional_param(param: &FnParam) -> bool {
+    match param {
+        FnParam::Typed(ty) => TypeInfo::new(&ty.ty)
+            .is_some_and(|info| info.ref_kind().is_none() && info.name().starts_with("Option<")),
+        FnParam::Receiver(_) => false,
+    }
+}
+
+fn check_variadic_params(f: &venial::Function) {
+    let last = f.params.len().saturating_sub(1);
+    for (i, param) in f.params.iter().enumerate() {
+        let FnParam::Typed(ty) = &param.0 else {
+            continue;
+        };
+        let is_variadic = TypeInfo::new(&ty.ty)
+            .is_some_and(|info| info.name().starts_with("Variadic<") || info.name() == "MultiValue");
+        if is_variadic && i != last {
+            panic!("variadic parameter {} of {} must be the last parameter", ty.name, f.name);
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExportKind {
+    Method,
+    Meta,
+    Getter,
+    Setter,
+}
+
+fn attr_name_arg(f: &venial::Function, attr: &venial::Attribute, attr_name: &str) -> Option<String> {
+    match &attr.value {
+        venial::AttributeValue::Empty => None,
+        venial::AttributeValue::Group(_, tokens) => match tokens.as_slice() {
+            [TokenTree::Ident(ident)] => Some(ident.to_string()),
+            _ => panic!("invalid {0} attribute on {1}, expected #[{0}(name)]", attr_name, f.name),
+        },
+        _ => panic!("invalid {0} attribute on {1}, expected #[{0}(name)]", attr_name, f.name),
+    }
+}
+
+fn func_name_arg(f: &venial::Function, attr: &venial::Attribute) -> Option<String> {
+    match &attr.value {
+        venial::AttributeValue::Empty => None,
+        venial::AttributeValue::Group(_, tokens) => match tokens.as_slice() {
+            [TokenTree::Ident(key), TokenTree::Punct(eq), TokenTree::Literal(lit)] if key == "name" && eq.as_char() == '=' => {
+                match syn::parse2::<syn::LitStr>(TokenTree::Literal(lit.clone()).into()) {
+                    Ok(lit) => Some(lit.value()),
+                    Err(_) => panic!("invalid func attribute on {}, name must be a string literal", f.name),
+                }
+            }
+            _ => panic!("invalid func attribute on {}, expected #[func] or #[func(name = \"...\")]", f.name),
+        },
+        _ => panic!("invalid func attribute on {}, expected #[func] or #[func(name = \"...\")]", f.name),
+    }
+}
+
+fn take_export_attr(f: &mut venial::Function) -> Option<(ExportKind, String)> {
+    let mut export: Option<(ExportKind, String)> = None;
+    let fn_name = f.name.to_string();
+    let attrs = std::mem::take(&mut f.attributes);
+
+    for attr in attrs {
+        let attr_name = match attr.path.as_slice() {
+            [TokenTree::Ident(ident)] => ident.to_string(),
+            _ => {
+                f.attributes.push(attr);
+                continue;
+            }
+        };
+
+        let value = match attr_name.as_str() {
+            "meta" => {
+                let Some(name) = attr_name_arg(f, &attr, "meta") else {
+                    panic!("invalid meta attribute on {}, expected #[meta(name)]", f.name);
+                };
+                let name = name.trim_start_matches("__").to_string();
+                if !META_METHODS.contains(&name.as_str()) {
+                    panic!("unsupported metamethod __{} on {}", name, f.name);
+                }
+                (ExportKind::Meta, format!("__{}", name))
+            }
+            "get" => {
+                let name = attr_name_arg(f, &attr, "get")
+                    .unwrap_or_else(|| fn_name.strip_prefix("get_").unwrap_or(&fn_name).to_string());
+                (ExportKind::Getter, name)
+            }
+            "func" => {
+                let name = func_name_arg(f, &attr).unwrap_or_else(|| fn_name.clone());
+                (ExportKind::Method, name)
+            }
+            "set" => {
+                let name = attr_name_arg(f, &attr, "set")
+                    .unwrap_or_else(|| fn_name.strip_prefix("set_").unwrap_or(&fn_name).to_string());
+                (ExportKind::Setter, name)
+            }
+            _ => {
+                f.attributes.push(attr);
+                continue;
+            }
+        };
+
+        if export.is_some() {
+            panic!("{} can only have one of #[func], #[meta], #[get] or #[set]", f.name);
+        }
+        export = Some(value);
+    }
+
+    export
+}
+
+fn check_duplicate_names(methods: &[(venial::Function, ExportKind, String)]) {
+    let mut seen: Vec<(&str, &str)> = vec![];
+    for (f, kind, lua_name) in methods {
+        let namespace = match kind {
+            ExportKind::Method | ExportKind::Getter => "__index",
+            ExportKind::Setter => "__newindex",
+            ExportKind::Meta => "metatable",
+        };
+
+        if seen.contains(&(namespace, lua_name.as_str())) {
+            panic!("duplicate lua name {} exported by {}", lua_name, f.name);
+        }
+        seen.push((namespace, lua_name.as_str()));
+    }
+}
+
+fn reg_array(ident: &syn::Ident, list: &[TokenStream], extra: TokenStream) -> (TokenStream, LitInt) {
+    let count = list.len() + if extra.is_empty() { 1 } else { 2 };
+    let count = LitInt::new(format!("{}", count).as_str(), Span::call_site());
+
+    let array = quote! {
+        static #ident: [ljr::SyncLuaReg; #count] = [
+            #(#list,)*
+            #extra
+            ljr::SyncLuaReg(ljr::sys::luaL_Reg {
+                name: std::ptr::null(),
+                func: ljr::dummy_trampoline,
+            })
+        ];
+    };
+    (array, count)
+}
+
 pub fn generate_user_data(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let parsed_item = try_or_return!(item, parse_item(item.clone()).ok());
     let impl_block = try_or_return!(item, parsed_item.as_impl());
@@ -38,24 +188,47 @@ pub fn generate_user_data(_attr: TokenStream, item: TokenStream) -> TokenStream
     };
     let ud_ty = impl_block.self_ty.clone();
 
-    let methods = impl_block.body_items.iter().filter_map(|item| match item {
-        venial::ImplMember::AssocFunction(f) => Some(f),
-        _ => None,
-    });
+    let mut impl_block = impl_block.clone();
+    let mut exports = vec![];
+    for item in impl_block.body_items.iter_mut() {
+        if let venial::ImplMember::AssocFunction(f) = item {
+            let export = take_export_attr(f);
+            exports.push((f.clone(), export));
+        }
+    }
 
-    let regs = methods.map(|m| {
+    let has_func_attr = exports.iter().any(|(_, export)| matches!(export, Some((ExportKind::Method, _))));
+    let methods: Vec<_> = exports.into_iter().filter_map(|(f, export)| match export {
+        Some((kind, lua_name)) => Some((f, kind, lua_name)),
+        None if has_func_attr => None,
+        None => {
+            let lua_name = f.name.to_string();
+            Some((f, ExportKind::Method, lua_name))
+        }
+    }).collect();
+    check_duplicate_names(&methods);
+    methods.iter().for_each(|(f, _, _)| check_variadic_params(f));
+
+    let regs = methods.iter().map(|(m, kind, lua_name)| {
         let fn_sym = &m.name;
         let mut call_args: Vec<TokenStream> = vec![];
-        let method_name = string_to_cstr_lit(m.name.to_string());
+        let method_name = string_to_cstr_lit(lua_name.clone());
+        let drop_extra_args = if *kind == ExportKind::Meta && (lua_name == "__unm" || lua_name == "__len") {
+            quote! { unsafe { ljr::sys::lua_settop(ptr, 1) }; }
+        } else if *kind == ExportKind::Meta && COMMUTATIVE_META_METHODS.contains(&lua_name.as_str()) {
+            quote! { unsafe { ljr::helper::swap_operands::<#ud_ty>(ptr) }; }
+        } else {
+            quote! {}
+        };
         let mut borrow_steps: Vec<TokenStream> = vec![];
         let mut safe_args: Vec<TokenStream> = vec![];
 
-        let arg_c = {
-            let len_expr_list: Vec<TokenStream> = m.params.iter()
+        let (min_args, max_args, variadic) = {
+            let from_lua_list: Vec<(TokenStream, bool)> = m.params.iter()
                 .filter_map(|p| {
-                    match &p.0 {
+                    let ty = match &p.0 {
                         FnParam::Receiver(_) => {
-                            Some(quote! { <StackUd<#ud_ty> as ljr::from_lua::FromLua>::LEN })
+                            Some(quote! { <StackUd<#ud_ty> as ljr::from_lua::FromLua> })
                         },
                         FnParam::Typed(ty) => {
                             let arg_ty = &ty.ty;
@@ -71,13 +244,13 @@ pub fn generate_user_data(_attr: TokenStream, item: TokenStream) -> TokenStream
                                 if ty_name == "Lua" {
                                     None
                                 } else if ty_name == "str" {
-                                    Some(quote! { <ljr::lstr::StackStr as ljr::from_lua::FromLua>::LEN })
+                                    Some(quote! { <ljr::lstr::StackStr as ljr::from_lua::FromLua> })
                                 } else if ty_name == "[u8]" {
-                                    Some(quote! { <ljr::lstr::StackStr as ljr::from_lua::FromLua>::LEN })
+                                    Some(quote! { <ljr::lstr::StackStr as ljr::from_lua::FromLua> })
                                 } else if SPECIAL_TYPES.iter().any(|n| type_info.name().starts_with(n)) {
-                                    Some(quote! { <#inner_ty as ljr::from_lua::FromLua>::LEN })
+                                    Some(quote! { <#inner_ty as ljr::from_lua::FromLua> })
                                 } else {
-                                    Some(quote! { <StackUd<#inner_ty> as ljr::from_lua::FromLua>::LEN })
+                                    Some(quote! { <StackUd<#inner_ty> as ljr::from_lua::FromLua> })
                                 }
                             } else {
                                 if type_info.name().starts_with("Option<") {
@@ -86,28 +259,41 @@ pub fn generate_user_data(_attr: TokenStream, item: TokenStream) -> TokenStream
                                     let opt_gen_ty_name = opt_generic.name();
                                     if opt_generic.ref_kind().is_some() {
                                         if opt_gen_ty_name == "str" ||  opt_gen_ty_name == "[u8]" {
-                                            Some(quote! { <StackStr as ljr::from_lua::FromLua>::LEN })
+                                            Some(quote! { <StackStr as ljr::from_lua::FromLua> })
                                         } else if SPECIAL_TYPES.iter().any(|n| opt_gen_ty_name.starts_with(n)) {
-                                            Some(quote! { <#inner_ty as ljr::from_lua::FromLua>::LEN })
+                                            Some(quote! { <#inner_ty as ljr::from_lua::FromLua> })
                                         } else {
-                                            Some(quote! { <StackUd<#inner_ty> as ljr::from_lua::FromLua>::LEN })
+                                            Some(quote! { <StackUd<#inner_ty> as ljr::from_lua::FromLua> })
                                         }
                                     } else {
-                                        Some(quote! { <#arg_ty as ljr::from_lua::FromLua>::LEN })
+                                        Some(quote! { <#arg_ty as ljr::from_lua::FromLua> })
                                     }
                                 } else {
-                                    Some(quote! { <#arg_ty as ljr::from_lua::FromLua>::LEN })
+                                    Some(quote! { <#arg_ty as ljr::from_lua::FromLua> })
                                 }
                             }
                         },
-                    }
+                    };
+                    ty.map(|ty| (ty, is_optional_param(&p.0)))
                 }).collect();
-            
-            if len_expr_list.is_empty() {
-                quote! { 0 }
+
+            let required = from_lua_list.iter().rposition(|(_, optional)| !optional).map_or(0, |i| i + 1);
+            let lens: Vec<TokenStream> = from_lua_list.iter().map(|(ty, _)| quote! { #ty::LEN }).collect();
+            let sum_lens = |lens: &[TokenStream]| {
+                if lens.is_empty() {
+                    quote! { 0 }
+                } else {
+                    quote! { (#(#lens)+*) as usize }
+                }
+            };
+
+            let variadic = if from_lua_list.is_empty() {
+                quote! { false }
             } else {
-                quote! { (#(#len_expr_list)+*) as usize }
-            }
+                let tys = from_lua_list.iter().map(|(ty, _)| ty);
+                quote! { #(#tys::VARIADIC)||* }
+            };
+            (sum_lens(&lens[..required]), sum_lens(&lens), variadic)
         };
 
         for param in m.params.iter() {
@@ -298,8 +484,13 @@ pub fn generate_user_data(_attr: TokenStream, item: TokenStream) -> TokenStream
         }
 
         let final_block = quote! {
-            ljr::helper::catch(ptr, move || {
-                ljr::helper::check_arg_count(ptr, #arg_c)?;
+            let __call = move || {
+                #drop_extra_args
+                unsafe { ljr::helper::check_arg_range(
+                    ptr,
+                    #min_args,
+                    if #variadic { None } else { Some(#max_args) },
+                ) }?;
 
                 #(#safe_args)*
 
@@ -308,46 +499,89 @@ pub fn generate_user_data(_attr: TokenStream, item: TokenStream) -> TokenStream
                 #(#borrow_steps)*
 
                 Ok(#ud_ty::#fn_sym(#(#call_args),*))
-            })
+            };
+            unsafe { ljr::helper::catch(ptr, __call) }
         };
 
-        quote! {
-            ljr::SyncLuaReg(ljr::sys::luaL_Reg {
-                name: #method_name.as_ptr() as _,
-                func: {
+        let has_receiver = m.params.iter().any(|p| matches!(p.0, FnParam::Receiver(_)));
+        let (trampoline, func) = if *kind == ExportKind::Method && !has_receiver {
+            let trampoline_ident = format_ident!("__{}_{}_trampoline", ud_name.to_lowercase(), fn_sym);
+            let trampoline = quote! {
+                #[doc(hidden)]
+                unsafe extern "C-unwind" fn #trampoline_ident(ptr: *mut ljr::sys::lua_State) -> std::ffi::c_int {
+                    #final_block
+                }
+            };
+            (Some(trampoline), quote! { #trampoline_ident })
+        } else {
+            (None, quote! {
+                {
                     unsafe extern "C-unwind" fn trampoline(ptr: *mut ljr::sys::lua_State) -> std::ffi::c_int {
                         #final_block
                     }
                     trampoline
                 }
             })
-        }
+        };
+
+        let reg = quote! {
+            ljr::SyncLuaReg(ljr::sys::luaL_Reg {
+                name: #method_name.as_ptr() as _,
+                func: #func,
+            })
+        };
+        (*kind, trampoline, reg)
     });
 
-    let mut count = 2;
-    let mut reg_list = quote! {};
-    for reg in regs {
-        reg_list.extend(quote! { #reg, });
-        count += 1;
+    let mut reg_list = vec![];
+    let mut meta_reg_list = vec![];
+    let mut getter_reg_list = vec![];
+    let mut setter_reg_list = vec![];
+    let mut static_reg_list = vec![];
+    let mut static_trampolines = vec![];
+    for (kind, trampoline, reg) in regs {
+        match kind {
+            ExportKind::Method => {
+                if let Some(trampoline) = trampoline {
+                    static_trampolines.push(trampoline);
+                    static_reg_list.push(reg.clone());
+                }
+                reg_list.push(reg);
+            }
+            ExportKind::Meta => meta_reg_list.push(reg),
+            ExportKind::Getter => getter_reg_list.push(reg),
+            ExportKind::Setter => setter_reg_list.push(reg),
+        }
     }
 
-    let regs_ident = format_ident!("{}_REGS", ud_name.to_uppercase());
-    let regs_count = LitInt::new(format!("{}", count).as_str(), Span::call_site());
+    let ud_upper = ud_name.to_uppercase();
+    let regs_ident = format_ident!("{}_REGS", ud_upper);
+    let meta_regs_ident = format_ident!("{}_META_REGS", ud_upper);
+    let getter_regs_ident = format_ident!("{}_GETTER_REGS", ud_upper);
+    let setter_regs_ident = format_ident!("{}_SETTER_REGS", ud_upper);
+    let static_regs_ident = format_ident!("{}_STATIC_REGS", ud_upper);
+
+    let (regs_array, regs_count) = reg_array(&regs_ident, &reg_list, quote! {
+        ljr::SyncLuaReg(ljr::sys::luaL_Reg {
+            name: concat!("__META_", stringify!(#ud_ty), "\0").as_ptr() as _,
+            func: ljr::dummy_trampoline,
+        }),
+    });
+    let (meta_regs_array, meta_regs_count) = reg_array(&meta_regs_ident, &meta_reg_list, quote! {});
+    let (getter_regs_array, getter_regs_count) = reg_array(&getter_regs_ident, &getter_reg_list, quote! {});
+    let (setter_regs_array, setter_regs_count) = reg_array(&setter_regs_ident, &setter_reg_list, quote! {});
+    let (static_regs_array, static_regs_count) = reg_array(&static_regs_ident, &static_reg_list, quote! {});
 
     quote! {
-        #item
+        #impl_block
 
-        static #regs_ident: [ljr::SyncLuaReg; #regs_count] = [
-            #reg_list
-            ljr::SyncLuaReg(ljr::sys::luaL_Reg {
-                name: concat!("__META_", stringify!(#ud_ty), "\0").as_ptr() as _,
-                func: ljr::dummy_trampoline,
-            }),
-            ljr::SyncLuaReg(ljr::sys::luaL_Reg {
-                name: std::ptr::null(),
-                func: ljr::dummy_trampoline,
-            })
-        ];
+        #(#static_trampolines)*
+
+        #regs_array
+        #meta_regs_array
+        #getter_regs_array
+        #setter_regs_array
+        #static_regs_array
 
         impl ljr::UserData for #ud_ty {
             #[inline(always)]
@@ -359,7 +593,115 @@ pub fn generate_user_data(_attr: TokenStream, item: TokenStream) -> TokenStream
             fn functions() -> &'static [ljr::sys::luaL_Reg] {
                 unsafe { &*(&#regs_ident as *const [ljr::SyncLuaReg; #regs_count] as *const [ljr::sys::luaL_Reg; #regs_count]) }
             }
+
+            #[inline(always)]
+            fn meta_functions() -> &'static [ljr::sys::luaL_Reg] {
+                unsafe { &*(&#meta_regs_ident as *const [ljr::SyncLuaReg; #meta_regs_count] as *const [ljr::sys::luaL_Reg; #meta_regs_count]) }
+            }
+
+            #[inline(always)]
+            fn getters() -> &'static [ljr::sys::luaL_Reg] {
+                unsafe { &*(&#getter_regs_ident as *const [ljr::SyncLuaReg; #getter_regs_count] as *const [ljr::sys::luaL_Reg; #getter_regs_count]) }
+            }
+
+            #[inline(always)]
+            fn setters() -> &'static [ljr::sys::luaL_Reg] {
+                unsafe { &*(&#setter_regs_ident as *const [ljr::SyncLuaReg; #setter_regs_count] as *const [ljr::sys::luaL_Reg; #setter_regs_count]) }
+            }
+
+            #[inline(always)]
+            fn static_functions() -> &'static [ljr::sys::luaL_Reg] {
+                unsafe { &*(&#static_regs_ident as *const [ljr::SyncLuaReg; #static_regs_count] as *const [ljr::sys::luaL_Reg; #static_regs_count]) }
+            }
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_func_attr_selects_exports() {
+        let output = generate_user_data(quote!(), quote! {
+            impl Test {
+                #[func]
+                fn exported(&self) -> i32 { 1 }
+
+                #[func(name = "renamedFn")]
+                fn renamed(&self) -> i32 { 2 }
+
+                fn helper(&self) -> i32 { 3 }
+            }
+        }).to_string();
+
+        assert!(output.contains("b\"exported\\0\""));
+        assert!(output.contains("b\"renamedFn\\0\""));
+        assert!(!output.contains("b\"helper\\0\""));
+        assert!(!output.contains("# [func"));
+    }
+
+    #[test]
+    fn test_untagged_exports_all() {
+        let output = generate_user_data(quote!(), quote! {
+            impl Test {
+                fn a(&self) -> i32 { 1 }
+                fn b(&self) -> i32 { 2 }
+            }
+        }).to_string();
+
+        assert!(output.contains("b\"a\\0\""));
+        assert!(output.contains("b\"b\\0\""));
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate lua name value")]
+    fn test_duplicate_lua_name() {
+        generate_user_data(quote!(), quote! {
+            impl Test {
+                #[func]
+                fn value(&self) -> i32 { 1 }
+
+                #[func(name = "value")]
+                fn other_value(&self) -> i32 { 2 }
+            }
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate lua name x")]
+    fn test_duplicate_getter_and_method() {
+        generate_user_data(quote!(), quote! {
+            impl Test {
+                #[get]
+                fn x(&self) -> i32 { 1 }
+
+                #[func(name = "x")]
+                fn other_x(&self) -> i32 { 2 }
+            }
+        });
+    }
+
+    #[test]
+    fn test_static_trampoline_generated_once() {
+        let output = generate_user_data(quote!(), quote! {
+            impl Test {
+                fn new() -> Test { Test }
+                fn value(&self) -> i32 { 1 }
+            }
+        }).to_string();
+
+        assert_eq!(output.matches("fn __test_new_trampoline").count(), 1);
+        assert_eq!(output.matches("func : __test_new_trampoline").count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "variadic parameter rest of log must be the last parameter")]
+    fn test_variadic_param_not_last() {
+        generate_user_data(quote!(), quote! {
+            impl Test {
+                fn log(&self, rest: Variadic<String>, level: i32) {}
+            }
+        });
+    }
+}
@@ -16,8 +16,9 @@ pub fn module(attr: TokenStream, item: TokenStream) -> TokenStream {
     let impl_body = if let Some(ty) = ret_ty {
         quote! {
             let opt_value = #name(&mut lua);
+            let top = unsafe { ljr::sys::lua_gettop(ptr) };
             <#ty as ljr::to_lua::ToLua>::to_lua(opt_value, ptr);
-            <#ty as ljr::to_lua::ToLua>::len()
+            unsafe { ljr::sys::lua_gettop(ptr) - top }
         }
     } else {
         quote! {
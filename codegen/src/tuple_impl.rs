@@ -2,6 +2,8 @@ use proc_macro2::{Span, TokenStream};
 use quote::{format_ident, quote};
 use syn::Ident;
 
+pub const MAX_TUPLE_LEN: usize = 25;
+
 fn gen_return_value(last_letter: char) -> TokenStream {
     let mut values = vec![];
     ('A'..=last_letter)
@@ -30,15 +32,15 @@ fn gen_cast(letter: char) -> TokenStream {
 }
 
 pub fn generate_from_lua_tuple_impl(_: TokenStream) -> TokenStream {
-    let max = 5;
     let mut impls = vec![];
     let alphabet: Vec<char> = (b'A'..b'Z').map(|c| c as char).collect();
 
-    (2..max).for_each(|n| {
+    (2..=MAX_TUPLE_LEN).for_each(|n| {
         let mut letters_a = vec![];
         let mut where_ch = vec![];
         let mut cast_impl = vec![];
         let mut len = vec![];
+        let mut variadic = vec![];
 
         (0..n).for_each(|i| {
             let letter = alphabet[i];
@@ -48,8 +50,10 @@ pub fn generate_from_lua_tuple_impl(_: TokenStream) -> TokenStream {
             cast_impl.push(gen_cast(letter));
             where_ch.push(quote!(#ch: FromLua));
             len.push(quote!(#ch::LEN));
+            variadic.push(quote!(#ch::VARIADIC));
         });
 
+        let inner_variadic = &variadic[..n - 1];
         let return_value = gen_return_value(alphabet[n - 1]);
         let letters_b = letters_a.clone();
         impls.push(quote! {
@@ -58,8 +62,16 @@ pub fn generate_from_lua_tuple_impl(_: TokenStream) -> TokenStream {
                 #(#where_ch,)*
             {
                 const LEN: i32 = 0 #(+ #len)*;
+                const VARIADIC: bool = false #(|| #variadic)*;
 
                 fn try_from_lua(ptr: *mut crate::sys::lua_State, idx: i32) -> Result<Self, Error> {
+                    const {
+                        assert!(
+                            !(false #(|| #inner_variadic)*),
+                            "only the last tuple element can be variadic"
+                        )
+                    };
+
                     let top = unsafe { crate::sys::lua_gettop(ptr) };
                     let mut idx = {
                         if idx.is_negative() {
@@ -85,11 +97,10 @@ pub fn generate_from_lua_tuple_impl(_: TokenStream) -> TokenStream {
 }
 
 pub fn generate_to_lua_tuple_impl(_attr: TokenStream) -> TokenStream {
-    let max = 5;
     let mut parts = vec![];
     let alphabet: Vec<char> = (b'A'..b'Z').map(|c| c as char).collect();
 
-    (2..max).for_each(|n| {
+    (2..=MAX_TUPLE_LEN).for_each(|n| {
         let mut state_push = vec![];
         let mut letters_a = vec![];
         let mut letters_b = vec![];
@@ -143,11 +154,10 @@ pub fn generate_to_lua_tuple_impl(_attr: TokenStream) -> TokenStream {
 }
 
 pub fn generate_value_arg_tuple_impl(_: TokenStream) -> TokenStream {
-    let max = 26;
     let mut impls = vec![];
     let alphabet: Vec<char> = (b'A'..b'Z').map(|c| c as char).collect();
 
-    (2..max).for_each(|n| {
+    (2..=MAX_TUPLE_LEN).for_each(|n| {
         let mut letters_a = vec![];
         let mut where_ch = vec![];
         let mut cast_impl = vec![];
@@ -173,3 +183,29 @@ pub fn generate_value_arg_tuple_impl(_: TokenStream) -> TokenStream {
 
     quote!(#(#impls)*)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn has_arity(output: &TokenStream, trait_name: &str, n: usize) -> bool {
+        let letters: Vec<String> = (b'A'..).take(n).map(|c| (c as char).to_string()).collect();
+        let tuple = format!("{} for ({} ,)", trait_name, letters.join(" , "));
+        output.to_string().contains(&tuple)
+    }
+
+    #[test]
+    fn test_tuple_arities_match() {
+        let outputs = [
+            (generate_from_lua_tuple_impl(quote!()), "FromLua"),
+            (generate_to_lua_tuple_impl(quote!()), "ToLua"),
+            (generate_value_arg_tuple_impl(quote!()), "ValueArg"),
+        ];
+        for (output, trait_name) in &outputs {
+            assert!(!has_arity(output, trait_name, 1), "{trait_name}");
+            assert!(has_arity(output, trait_name, 2), "{trait_name}");
+            assert!(has_arity(output, trait_name, MAX_TUPLE_LEN), "{trait_name}");
+            assert!(!has_arity(output, trait_name, MAX_TUPLE_LEN + 1), "{trait_name}");
+        }
+    }
+}
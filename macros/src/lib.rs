@@ -24,3 +24,24 @@ pub fn generate_value_arg_tuple_impl(attr: TokenStream) -> TokenStream {
 pub fn module(attr: TokenStream, item: TokenStream) -> TokenStream {
     codegen::module::module(attr.into(), item.into()).into()
 }
+
+/// Embeds every `.lua` file under a directory as an `ljr::lua::Bundle`.
+///
+/// Edits to embedded files trigger a rebuild, but new files are only picked up
+/// once the invoking crate is rebuilt for another reason; add a `build.rs` that
+/// prints `cargo:rerun-if-changed=<dir>` to track them. Passing `check` parses
+/// each file at compile time and requires the `check` feature.
+#[proc_macro]
+pub fn include_lua(input: TokenStream) -> TokenStream {
+    codegen::bundle::include_lua(input.into()).into()
+}
+
+#[proc_macro_derive(ToLua, attributes(lua))]
+pub fn derive_to_lua(item: TokenStream) -> TokenStream {
+    codegen::derive::derive_to_lua(item.into()).into()
+}
+
+#[proc_macro_derive(FromLua, attributes(lua))]
+pub fn derive_from_lua(item: TokenStream) -> TokenStream {
+    codegen::derive::derive_from_lua(item.into()).into()
+}
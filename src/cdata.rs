@@ -0,0 +1,473 @@
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_void},
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use crate::{
+    Borrowed, Mode, Owned,
+    error::{Error, UnwrapDisplay},
+    from_lua::FromLua,
+    helper,
+    int64::{self, LUA_TCDATA},
+    is_type::IsType,
+    lua::InnerLua,
+    owned_value::LuaInnerHandle,
+    prelude::OwnedValue,
+    stack_guard::StackGuard,
+    sys,
+    to_lua::ToLua,
+};
+
+const CDATA_NAME_KEY: usize = 0x6C6A72_07;
+
+const CDATA_HELPERS: &CStr = c"
+local ffi, tostring = ...
+return function(v)
+    return (tostring(ffi.typeof(v)):match('^ctype<(.*)>$'))
+end";
+
+/// # Safety
+///
+/// The type must be `#[repr(C)]` and match the layout declared by `CDEF` for the
+/// ctype `NAME`, since values are copied in and out of cdata byte for byte.
+pub unsafe trait CType: Copy + 'static {
+    const NAME: &'static str;
+    const CDEF: &'static str;
+}
+
+unsafe fn push_name_helper(ptr: *mut sys::lua_State) -> Result<(), Error> {
+    unsafe {
+        helper::try_check_stack(ptr, 4)?;
+        sys::lua_pushlightuserdata(ptr, CDATA_NAME_KEY as *mut c_void);
+        sys::lua_rawget(ptr, sys::LUA_REGISTRYINDEX);
+        if sys::lua_isfunction(ptr, -1) != 0 {
+            return Ok(());
+        }
+        sys::lua_pop(ptr, 1);
+
+        let g = StackGuard::new(ptr);
+        if sys::luaL_loadstring(ptr, CDATA_HELPERS.as_ptr()) != 0 {
+            return Err(Error::from_stack(ptr, -1));
+        }
+        int64::push_ffi(ptr)?;
+        sys::lua_getfield(ptr, sys::LUA_GLOBALSINDEX, c"tostring".as_ptr());
+        helper::pcall(ptr, 2, 1)?;
+
+        sys::lua_pushlightuserdata(ptr, CDATA_NAME_KEY as *mut c_void);
+        sys::lua_pushvalue(ptr, -2);
+        sys::lua_rawset(ptr, sys::LUA_REGISTRYINDEX);
+
+        g.commit();
+        Ok(())
+    }
+}
+
+pub(crate) unsafe fn push_ffi_fn(ptr: *mut sys::lua_State, name: &CStr) -> Result<(), Error> {
+    unsafe {
+        helper::try_check_stack(ptr, 2)?;
+        int64::push_ffi(ptr)?;
+        sys::lua_getfield(ptr, -1, name.as_ptr());
+        sys::lua_remove(ptr, -2);
+        Ok(())
+    }
+}
+
+pub(crate) unsafe fn is_declared(ptr: *mut sys::lua_State, name: &CStr) -> Result<bool, Error> {
+    unsafe {
+        helper::try_check_stack(ptr, 2)?;
+        let _g = StackGuard::new(ptr);
+        push_ffi_fn(ptr, c"typeof")?;
+        sys::lua_pushstring(ptr, name.as_ptr());
+        Ok(helper::pcall(ptr, 1, 0).is_ok())
+    }
+}
+
+pub trait CDataState {
+    type State;
+}
+
+pub trait CDataAccess {
+    fn try_ptr(&self) -> Result<*mut sys::lua_State, Error>;
+
+    fn data_ptr(&self) -> *mut c_void;
+
+    fn push(&self, ptr: *mut sys::lua_State);
+
+    fn try_call<R>(
+        &self,
+        prepare: impl FnOnce(*mut sys::lua_State) -> Result<i32, Error>,
+        read: impl FnOnce(*mut sys::lua_State) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let ptr = self.try_ptr()?;
+        unsafe {
+            helper::try_check_stack(ptr, 1)?;
+            let _g = StackGuard::new(ptr);
+            let nargs = prepare(ptr)?;
+            helper::try_check_stack(ptr, 1)?;
+            self.push(ptr);
+            helper::pcall(ptr, nargs + 1, 1)?;
+            read(ptr)
+        }
+    }
+
+    fn try_type_name(&self) -> Result<String, Error> {
+        self.try_call(
+            |ptr| unsafe { push_name_helper(ptr).map(|_| 0) },
+            |ptr| String::try_from_lua(ptr, -1),
+        )
+    }
+
+    fn try_is<T: CType>(&self) -> Result<bool, Error> {
+        let name = CString::new(T::NAME)?;
+        if !unsafe { is_declared(self.try_ptr()?, &name)? } {
+            return Ok(false);
+        }
+        self.try_call(
+            |ptr| unsafe {
+                push_ffi_fn(ptr, c"istype")?;
+                sys::lua_pushstring(ptr, name.as_ptr());
+                Ok(1)
+            },
+            |ptr| unsafe { Ok(sys::lua_toboolean(ptr, -1) != 0) },
+        )
+    }
+
+    fn try_as_i64(&self) -> Result<i64, Error> {
+        self.try_call(
+            |ptr| unsafe {
+                push_ffi_fn(ptr, c"cast")?;
+                sys::lua_pushstring(ptr, c"int64_t".as_ptr());
+                Ok(1)
+            },
+            |ptr| unsafe { Ok(*(sys::lua_topointer(ptr, -1) as *const i64)) },
+        )
+    }
+
+    fn try_as_f64(&self) -> Result<f64, Error> {
+        self.try_call(
+            |ptr| unsafe {
+                sys::lua_getfield(ptr, sys::LUA_GLOBALSINDEX, c"tonumber".as_ptr());
+                Ok(0)
+            },
+            |ptr| match unsafe { sys::lua_type(ptr, -1) } {
+                sys::LUA_TNUMBER => f64::try_from_lua(ptr, -1),
+                _ => Err(Error::UnexpectedType),
+            },
+        )
+    }
+
+    fn try_as_ptr<T>(&self) -> Result<*mut T, Error> {
+        let name = self.try_type_name()?;
+        let data = self.data_ptr();
+        if name.ends_with('*') || name.ends_with('&') || name.contains("(*)") {
+            Ok(unsafe { *(data as *const *mut T) })
+        } else {
+            Ok(data as *mut T)
+        }
+    }
+}
+
+pub struct BorrowedState {
+    ptr: *mut sys::lua_State,
+    idx: i32,
+    data_ptr: *mut c_void,
+}
+
+impl CDataState for Borrowed {
+    type State = BorrowedState;
+}
+
+impl CDataAccess for BorrowedState {
+    fn try_ptr(&self) -> Result<*mut sys::lua_State, Error> {
+        Ok(self.ptr)
+    }
+
+    fn data_ptr(&self) -> *mut c_void {
+        self.data_ptr
+    }
+
+    fn push(&self, ptr: *mut sys::lua_State) {
+        unsafe { sys::lua_pushvalue(ptr, self.idx) };
+    }
+}
+
+#[derive(Debug)]
+pub struct OwnedState {
+    lua: RefCell<Rc<InnerLua>>,
+    id: i32,
+    data_ptr: *mut c_void,
+}
+
+impl Drop for OwnedState {
+    fn drop(&mut self) {
+        if let Ok(ptr) = self.lua.borrow().try_state() {
+            unsafe { sys::luaL_unref(ptr, sys::LUA_REGISTRYINDEX, self.id) };
+        }
+    }
+}
+
+impl CDataState for Owned {
+    type State = OwnedState;
+}
+
+impl CDataAccess for OwnedState {
+    fn try_ptr(&self) -> Result<*mut sys::lua_State, Error> {
+        self.lua.borrow().try_state()
+    }
+
+    fn data_ptr(&self) -> *mut c_void {
+        self.data_ptr
+    }
+
+    fn push(&self, ptr: *mut sys::lua_State) {
+        unsafe { sys::lua_rawgeti(ptr, sys::LUA_REGISTRYINDEX, self.id as _) };
+    }
+}
+
+pub type StackCData = CData<Borrowed>;
+pub type CDataRef = CData<Owned>;
+
+pub struct CData<M>
+where
+    M: Mode + CDataState,
+    M::State: CDataAccess,
+{
+    state: M::State,
+}
+
+impl<M> CData<M>
+where
+    M: Mode + CDataState,
+    M::State: CDataAccess,
+{
+    #[inline]
+    pub fn try_type_name(&self) -> Result<String, Error> {
+        self.state.try_type_name()
+    }
+
+    #[inline]
+    pub fn type_name(&self) -> String {
+        self.try_type_name().unwrap_display()
+    }
+
+    #[inline]
+    pub fn try_is<T: CType>(&self) -> Result<bool, Error> {
+        self.state.try_is::<T>()
+    }
+
+    #[inline]
+    pub fn is<T: CType>(&self) -> bool {
+        self.try_is::<T>().unwrap_display()
+    }
+
+    #[inline]
+    pub fn try_as_i64(&self) -> Result<i64, Error> {
+        self.state.try_as_i64()
+    }
+
+    #[inline]
+    pub fn as_i64(&self) -> i64 {
+        self.try_as_i64().unwrap_display()
+    }
+
+    #[inline]
+    pub fn try_as_f64(&self) -> Result<f64, Error> {
+        self.state.try_as_f64()
+    }
+
+    #[inline]
+    pub fn as_f64(&self) -> f64 {
+        self.try_as_f64().unwrap_display()
+    }
+
+    #[inline]
+    pub fn try_as_ptr<T>(&self) -> Result<*mut T, Error> {
+        self.state.try_as_ptr()
+    }
+
+    #[inline]
+    pub fn as_ptr<T>(&self) -> *mut T {
+        self.try_as_ptr().unwrap_display()
+    }
+
+    pub fn try_with<T: CType, F: FnOnce(&T) -> R, R>(&self, f: F) -> Result<R, Error> {
+        if !self.try_is::<T>()? {
+            return Err(Error::UnexpectedType);
+        }
+        Ok(f(unsafe { &*(self.state.data_ptr() as *const T) }))
+    }
+
+    #[inline]
+    pub fn with<T: CType, F: FnOnce(&T) -> R, R>(&self, f: F) -> R {
+        self.try_with(f).unwrap_display()
+    }
+
+    pub fn try_with_mut<T: CType, F: FnOnce(&mut T) -> R, R>(&mut self, f: F) -> Result<R, Error> {
+        if !self.try_is::<T>()? {
+            return Err(Error::UnexpectedType);
+        }
+        Ok(f(unsafe { &mut *(self.state.data_ptr() as *mut T) }))
+    }
+
+    #[inline]
+    pub fn with_mut<T: CType, F: FnOnce(&mut T) -> R, R>(&mut self, f: F) -> R {
+        self.try_with_mut(f).unwrap_display()
+    }
+
+    #[inline]
+    pub fn try_read<T: CType>(&self) -> Result<T, Error> {
+        self.try_with(|v: &T| *v)
+    }
+
+    #[inline]
+    pub fn read<T: CType>(&self) -> T {
+        self.try_read().unwrap_display()
+    }
+}
+
+impl StackCData {
+    #[inline(always)]
+    pub fn try_to_owned(&self) -> Result<CDataRef, Error> {
+        CDataRef::try_from_lua(self.state.ptr, self.state.idx)
+    }
+
+    #[inline(always)]
+    pub fn to_owned(&self) -> CDataRef {
+        self.try_to_owned().unwrap_display()
+    }
+}
+
+impl CDataRef {
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        let lua = self.state.lua.clone();
+        let data_ptr = self.state.data_ptr;
+        let id = unsafe {
+            let ptr = lua.borrow().try_state()?;
+            helper::try_check_stack(ptr, 1)?;
+            sys::lua_rawgeti(ptr, sys::LUA_REGISTRYINDEX, self.state.id as _);
+            sys::luaL_ref(ptr, sys::LUA_REGISTRYINDEX)
+        };
+        Ok(Self {
+            state: OwnedState { lua, id, data_ptr },
+        })
+    }
+}
+
+impl Clone for CDataRef {
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_display()
+    }
+}
+
+unsafe impl FromLua for StackCData {
+    fn try_from_lua(ptr: *mut sys::lua_State, idx: i32) -> Result<Self, Error> {
+        unsafe {
+            let idx = sys::lua_absindex(ptr, idx);
+            if sys::lua_type(ptr, idx) == LUA_TCDATA {
+                let data_ptr = sys::lua_topointer(ptr, idx) as *mut c_void;
+                Ok(StackCData {
+                    state: BorrowedState { ptr, idx, data_ptr },
+                })
+            } else {
+                Err(Error::UnexpectedType)
+            }
+        }
+    }
+}
+
+unsafe impl FromLua for CDataRef {
+    fn try_from_lua(ptr: *mut sys::lua_State, idx: i32) -> Result<Self, Error> {
+        unsafe {
+            let idx = sys::lua_absindex(ptr, idx);
+            if sys::lua_type(ptr, idx) == LUA_TCDATA {
+                helper::try_check_stack(ptr, 1)?;
+                let lua = RefCell::new(InnerLua::from_ptr(ptr));
+                let data_ptr = sys::lua_topointer(ptr, idx) as *mut c_void;
+                sys::lua_pushvalue(ptr, idx);
+                let id = sys::luaL_ref(ptr, sys::LUA_REGISTRYINDEX);
+                Ok(CDataRef {
+                    state: OwnedState { lua, id, data_ptr },
+                })
+            } else {
+                Err(Error::UnexpectedType)
+            }
+        }
+    }
+}
+
+unsafe impl ToLua for &StackCData {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        InnerLua::try_ensure_context_raw(self.state.ptr, ptr)?;
+        unsafe { sys::lua_pushvalue(ptr, self.state.idx) };
+        Ok(())
+    }
+}
+
+unsafe impl ToLua for StackCData {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        unsafe { (&self).try_to_lua_unchecked(ptr) }
+    }
+}
+
+unsafe impl ToLua for &CDataRef {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        InnerLua::try_ensure_context_raw(self.state.lua.borrow().try_state()?, ptr)?;
+        unsafe { sys::lua_rawgeti(ptr, sys::LUA_REGISTRYINDEX, self.state.id as _) };
+        Ok(())
+    }
+}
+
+unsafe impl ToLua for CDataRef {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        unsafe { (&self).try_to_lua_unchecked(ptr) }
+    }
+}
+
+impl<M> IsType for CData<M>
+where
+    M: Mode + CDataState,
+    M::State: CDataAccess,
+{
+    fn is_type(ptr: *mut sys::lua_State, idx: i32) -> bool {
+        unsafe { sys::lua_type(ptr, idx) == LUA_TCDATA }
+    }
+}
+
+impl<M1, M2> PartialEq<CData<M2>> for CData<M1>
+where
+    M1: Mode + CDataState,
+    M1::State: CDataAccess,
+    M2: Mode + CDataState,
+    M2::State: CDataAccess,
+{
+    fn eq(&self, other: &CData<M2>) -> bool {
+        self.state.data_ptr() == other.state.data_ptr()
+    }
+}
+
+impl<M> Eq for CData<M>
+where
+    M: Mode + CDataState,
+    M::State: CDataAccess,
+{
+}
+
+impl<M> Hash for CData<M>
+where
+    M: Mode + CDataState,
+    M::State: CDataAccess,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.state.data_ptr().hash(state);
+    }
+}
+
+impl crate::owned_value::private::Sealed for CDataRef {}
+
+impl OwnedValue for CDataRef {
+    fn handle(&self) -> LuaInnerHandle<'_> {
+        LuaInnerHandle(&self.state.lua)
+    }
+}
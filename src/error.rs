@@ -2,9 +2,14 @@ use std::{
     cell::{BorrowError, BorrowMutError},
     ffi::NulError,
     fmt::Display,
+    mem::ManuallyDrop,
+    ops::Deref,
     str::Utf8Error,
+    thread::ThreadId,
 };
 
+use crate::{from_lua::FromLua, sys, value::ValueRef};
+
 pub const STACK_OVERFLOW_ERR: &'static str = "cannot grow Lua stack to required size";
 
 pub trait UnwrapDisplay<T> {
@@ -22,14 +27,16 @@ impl<T, E: Display> UnwrapDisplay<T> for Result<T, E> {
     }
 }
 
-#[derive(Debug, thiserror::Error, PartialEq)]
+#[derive(Debug, Clone, thiserror::Error, PartialEq)]
 pub enum Error {
     #[error("invalid string, interior nul byte found")]
     InvalidCString,
     #[error("lua error: unknown")]
     UnknownLuaError,
     #[error("lua error: {0}")]
-    LuaError(String),
+    LuaError(RuntimeError),
+    #[error("lua error: {0}")]
+    LuaValue(ErrorValue),
     #[error("unexpected type")]
     UnexpectedType,
     #[error("invalid syntax: {0}")]
@@ -54,7 +61,7 @@ pub enum Error {
     Utf8Error(#[from] Utf8Error),
     #[error("wrong number of arguments, expecting {0}, got {1}")]
     ArgumentCountMismatch(usize, usize),
-    #[error("invalid argument {0}, expected {0}")]
+    #[error("invalid argument {0}, expected {1}")]
     ArgumentTypeMismatch(usize, String),
     #[error("insufficient values on stack: type requires {0}, but only {1} are available")]
     InsufficientStackValues(i32, i32),
@@ -62,24 +69,332 @@ pub enum Error {
     TableIsEmpty,
     #[error("failed to create lua state, out of memory")]
     StateAllocationFailed,
+    #[error("memory limit exceeded")]
+    MemoryLimitExceeded,
     #[error("no metatable")]
     NoMetaTable,
+    #[error("cannot convert {0} to {1} without loss")]
+    InvalidNumberConversion(String, &'static str),
+    #[error("no writable property named {0}")]
+    PropertyNotWritable(String),
+    #[error("weak reference target has been collected")]
+    ValueCollected,
+    #[error("cannot resume non-suspended coroutine")]
+    CoroutineNotResumable,
+    #[error("execution limit exceeded")]
+    ExecutionLimitExceeded,
+    #[error("invalid field {0}: {1}")]
+    InvalidField(String, Box<Error>),
+    #[error("ctype {0} has size {1}, expected {2}")]
+    CTypeSizeMismatch(String, usize, usize),
+    #[error("unknown variant {0}")]
+    UnknownVariant(String),
+    #[cfg(feature = "serde")]
+    #[error("serialize error: {0}")]
+    SerializeError(String),
+    #[cfg(feature = "serde")]
+    #[error("deserialize error: {0}")]
+    DeserializeError(String),
     #[error("{0}")]
     Generic(String),
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub source: String,
+    pub line: Option<u32>,
+    pub name: Option<String>,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        match &self.name {
+            Some(name) => write!(f, ": in function '{}'", name),
+            None => write!(f, ": in ?"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    pub traceback: Vec<Frame>,
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if !self.traceback.is_empty() {
+            write!(f, "\nstack traceback:")?;
+            for frame in &self.traceback {
+                write!(f, "\n\t{}", frame)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Deref for RuntimeError {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.message
+    }
+}
+
+pub struct ErrorValue {
+    value: ManuallyDrop<Option<ValueRef>>,
+    owner: ThreadId,
+    repr: String,
+}
+
+// SAFETY: the inner `ValueRef` is only touched from the thread that created it; on any other
+// thread it is unreachable and leaked on drop.
+unsafe impl Send for ErrorValue {}
+unsafe impl Sync for ErrorValue {}
+
+impl ErrorValue {
+    pub fn new(value: ValueRef) -> Self {
+        Self {
+            repr: format!("{:?}", value),
+            value: ManuallyDrop::new(Some(value)),
+            owner: std::thread::current().id(),
+        }
+    }
+
+    fn is_owner(&self) -> bool {
+        self.owner == std::thread::current().id()
+    }
+
+    pub fn value(&self) -> Option<&ValueRef> {
+        if self.is_owner() {
+            self.value.as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn into_value(mut self) -> Option<ValueRef> {
+        if self.is_owner() {
+            self.value.take()
+        } else {
+            None
+        }
+    }
+}
+
+impl From<ValueRef> for ErrorValue {
+    fn from(value: ValueRef) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Clone for ErrorValue {
+    fn clone(&self) -> Self {
+        Self {
+            value: ManuallyDrop::new(self.value().cloned()),
+            owner: self.owner,
+            repr: self.repr.clone(),
+        }
+    }
+}
+
+impl Drop for ErrorValue {
+    fn drop(&mut self) {
+        if self.is_owner() {
+            unsafe { ManuallyDrop::drop(&mut self.value) };
+        }
+    }
+}
+
+impl PartialEq for ErrorValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.value(), other.value()) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Debug for ErrorValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.repr)
+    }
+}
+
+impl Display for ErrorValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.repr)
+    }
+}
+
 impl From<NulError> for Error {
     fn from(_: NulError) -> Self {
         Error::InvalidCString
     }
 }
 
+unsafe extern "C-unwind" fn wrapped_error_gc(ptr: *mut sys::lua_State) -> i32 {
+    unsafe {
+        let err_ptr = sys::lua_touserdata(ptr, 1) as *mut *mut Error;
+        if !err_ptr.is_null() && !(*err_ptr).is_null() {
+            std::mem::drop(Box::from_raw(*err_ptr));
+            *err_ptr = std::ptr::null_mut();
+        }
+    }
+    0
+}
+
+unsafe fn push_error_string(ptr: *mut sys::lua_State, idx: i32) {
+    unsafe {
+        match wrapped_error(ptr, idx) {
+            Some(err) => {
+                let msg = err.to_string();
+                sys::lua_pushlstring_(ptr, msg.as_ptr() as _, msg.len());
+            }
+            None => sys::lua_pushvalue(ptr, idx),
+        }
+    }
+}
+
+unsafe extern "C-unwind" fn wrapped_error_tostring(ptr: *mut sys::lua_State) -> i32 {
+    unsafe { push_error_string(ptr, 1) };
+    1
+}
+
+unsafe extern "C-unwind" fn wrapped_error_concat(ptr: *mut sys::lua_State) -> i32 {
+    unsafe {
+        sys::luaL_checkstack(ptr, 2, std::ptr::null());
+        push_error_string(ptr, 1);
+        push_error_string(ptr, 2);
+        sys::lua_concat(ptr, 2);
+    }
+    1
+}
+
+unsafe extern "C-unwind" fn wrapped_error_method(ptr: *mut sys::lua_State) -> i32 {
+    unsafe {
+        let nargs = sys::lua_gettop(ptr);
+        sys::luaL_checkstack(ptr, 2, std::ptr::null());
+        sys::lua_pushvalue(ptr, sys::lua_upvalueindex(1));
+        sys::lua_insert(ptr, 1);
+        if nargs > 0 {
+            push_error_string(ptr, 2);
+            sys::lua_replace(ptr, 2);
+        }
+        sys::lua_call(ptr, nargs, sys::LUA_MULTRET);
+        sys::lua_gettop(ptr)
+    }
+}
+
+unsafe extern "C-unwind" fn wrapped_error_index(ptr: *mut sys::lua_State) -> i32 {
+    unsafe {
+        sys::luaL_checkstack(ptr, 2, std::ptr::null());
+        sys::lua_pushstring(ptr, c"".as_ptr());
+        if sys::luaL_getmetafield(ptr, -1, c"__index".as_ptr()) == 0 {
+            sys::lua_pushnil(ptr);
+            return 1;
+        }
+        sys::lua_pushvalue(ptr, 2);
+        sys::lua_gettable(ptr, -2);
+        if sys::lua_isfunction(ptr, -1) == 0 {
+            sys::lua_pushnil(ptr);
+            return 1;
+        }
+        sys::lua_pushcclosure(ptr, wrapped_error_method, 1);
+    }
+    1
+}
+
+unsafe fn wrapped_error<'a>(ptr: *mut sys::lua_State, idx: i32) -> Option<&'a Error> {
+    unsafe {
+        if sys::lua_type(ptr, idx) != sys::LUA_TUSERDATA || sys::lua_checkstack(ptr, 2) == 0 {
+            return None;
+        }
+        if sys::lua_getmetatable(ptr, idx) == 0 {
+            return None;
+        }
+        sys::luaL_getmetatable(ptr, c"__LJR_ERROR".as_ptr());
+        let is_error = sys::lua_rawequal(ptr, -1, -2) != 0;
+        sys::lua_pop(ptr, 2);
+        if !is_error {
+            return None;
+        }
+
+        let err_ptr = sys::lua_touserdata(ptr, idx) as *mut *mut Error;
+        (*err_ptr).as_ref()
+    }
+}
+
 impl Error {
-    pub(crate) unsafe fn from_stack(ptr: *mut crate::sys::lua_State, idx: i32) -> Error {
-        if let Ok(msg) = <String as crate::from_lua::FromLua>::try_from_lua(ptr, idx) {
-            return Error::LuaError(msg);
-        } else {
-            return Error::UnknownLuaError;
+    pub(crate) unsafe fn from_stack(ptr: *mut sys::lua_State, idx: i32) -> Error {
+        if let Ok(message) = String::try_from_lua(ptr, idx) {
+            return Error::LuaError(RuntimeError {
+                message,
+                traceback: vec![],
+            });
+        }
+
+        if let Some(err) = unsafe { wrapped_error(ptr, idx) } {
+            return err.clone();
+        }
+
+        match ValueRef::try_from_lua(ptr, idx) {
+            Ok(value) => Error::LuaValue(ErrorValue::new(value)),
+            Err(_) => Error::UnknownLuaError,
+        }
+    }
+
+    pub(crate) unsafe fn push(self, ptr: *mut sys::lua_State) {
+        unsafe {
+            if let Error::LuaValue(value) = self {
+                let repr = value.repr.clone();
+                let pushed = match value.into_value() {
+                    Some(value) => crate::to_lua::ToLua::try_to_lua_unchecked(value, ptr),
+                    None => crate::to_lua::ToLua::try_to_lua_unchecked(repr, ptr),
+                };
+                if let Err(err) = pushed {
+                    err.push(ptr);
+                }
+                return;
+            }
+
+            let size = std::mem::size_of::<*mut Error>();
+            let err_ptr = sys::lua_newuserdata(ptr, size) as *mut *mut Error;
+            *err_ptr = Box::into_raw(Box::new(self));
+
+            if sys::luaL_newmetatable(ptr, c"__LJR_ERROR".as_ptr()) != 0 {
+                sys::lua_pushcfunction(ptr, wrapped_error_gc);
+                sys::lua_setfield(ptr, -2, c"__gc".as_ptr());
+                sys::lua_pushcfunction(ptr, wrapped_error_tostring);
+                sys::lua_setfield(ptr, -2, c"__tostring".as_ptr());
+                sys::lua_pushcfunction(ptr, wrapped_error_concat);
+                sys::lua_setfield(ptr, -2, c"__concat".as_ptr());
+                sys::lua_pushcfunction(ptr, wrapped_error_index);
+                sys::lua_setfield(ptr, -2, c"__index".as_ptr());
+            }
+            sys::lua_setmetatable(ptr, -2);
+        }
+    }
+
+    pub(crate) fn with_traceback(self, frames: Vec<Frame>) -> Error {
+        match self {
+            Error::LuaError(err) if err.traceback.is_empty() => Error::LuaError(RuntimeError {
+                traceback: frames,
+                ..err
+            }),
+            err => err,
+        }
+    }
+
+    pub(crate) fn in_field(self, field: &str) -> Error {
+        match self {
+            Error::InvalidField(path, err) => Error::InvalidField(format!("{field}.{path}"), err),
+            err => Error::InvalidField(field.to_string(), Box::new(err)),
         }
     }
 }
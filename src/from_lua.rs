@@ -3,6 +3,7 @@ use macros::generate_from_lua_tuple_impl;
 
 pub unsafe trait FromLua: Sized {
     const LEN: i32 = 1;
+    const VARIADIC: bool = false;
 
     fn try_from_lua(ptr: *mut sys::lua_State, idx: i32) -> Result<Self, Error>;
 
@@ -11,16 +12,35 @@ pub unsafe trait FromLua: Sized {
     }
 }
 
-unsafe impl FromLua for i32 {
-    fn try_from_lua(ptr: *mut crate::sys::lua_State, idx: i32) -> Result<Self, Error> {
-        if unsafe { sys::lua_isnumber(ptr, idx) != 0 } {
-            Ok(unsafe { sys::lua_tonumber(ptr, idx) } as i32)
-        } else {
-            Err(Error::UnexpectedType)
-        }
+pub(crate) fn integer_from_number<T: TryFrom<i128>>(n: f64) -> Option<T> {
+    if n.is_finite() && n.fract() == 0.0 {
+        T::try_from(n as i128).ok()
+    } else {
+        None
     }
 }
 
+macro_rules! impl_from_lua_int {
+    ($($ty:ty),*) => {
+        $(
+            unsafe impl FromLua for $ty {
+                fn try_from_lua(ptr: *mut crate::sys::lua_State, idx: i32) -> Result<Self, Error> {
+                    if unsafe { sys::lua_isnumber(ptr, idx) != 0 } {
+                        let n = unsafe { sys::lua_tonumber(ptr, idx) };
+                        integer_from_number(n).ok_or_else(|| {
+                            Error::InvalidNumberConversion(n.to_string(), stringify!($ty))
+                        })
+                    } else {
+                        Err(Error::UnexpectedType)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_lua_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
 unsafe impl FromLua for f32 {
     fn try_from_lua(ptr: *mut crate::sys::lua_State, idx: i32) -> Result<Self, Error> {
         if unsafe { sys::lua_isnumber(ptr, idx) != 0 } {
@@ -77,7 +97,7 @@ where
     const LEN: i32 = T::LEN;
 
     fn try_from_lua(ptr: *mut crate::sys::lua_State, idx: i32) -> Result<Self, Error> {
-        if unsafe { sys::lua_type(ptr, idx) } == sys::LUA_TNIL as i32 {
+        if unsafe { sys::lua_type(ptr, idx) } <= sys::LUA_TNIL as i32 {
             Ok(None)
         } else {
             <T as FromLua>::try_from_lua(ptr, idx).map(Some)
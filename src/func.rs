@@ -5,22 +5,58 @@ use std::{
 };
 
 use crate::{
-    Borrowed, Mode, Owned,
+    Borrowed, Mode, Owned, Weak,
     error::{Error, UnwrapDisplay},
     from_lua::FromLua,
     helper,
-    lua::{InnerLua, ValueArg},
+    lua::{InnerLua, Lua, ValueArg},
     owned_value::LuaInnerHandle,
     prelude::OwnedValue,
     stack_guard::StackGuard,
     sys,
+    table::TableRef,
     to_lua::ToLua,
+    weak::WeakSlot,
 };
 
 pub trait FuncState {
     type State;
 }
 
+unsafe extern "C-unwind" fn dump_writer(
+    _: *mut sys::lua_State,
+    p: *const std::ffi::c_void,
+    sz: usize,
+    ud: *mut std::ffi::c_void,
+) -> std::ffi::c_int {
+    unsafe {
+        let buf = &mut *(ud as *mut Vec<u8>);
+        buf.extend_from_slice(std::slice::from_raw_parts(p as *const u8, sz));
+    }
+    0
+}
+
+unsafe fn push_loaded_field(
+    ptr: *mut sys::lua_State,
+    lib: &std::ffi::CStr,
+    field: &std::ffi::CStr,
+) -> bool {
+    unsafe {
+        sys::lua_getfield(ptr, sys::LUA_REGISTRYINDEX, c"_LOADED".as_ptr());
+        if sys::lua_istable(ptr, -1) != 0 {
+            sys::lua_getfield(ptr, -1, lib.as_ptr());
+            sys::lua_remove(ptr, -2);
+        }
+        if sys::lua_istable(ptr, -1) == 0 {
+            sys::lua_pop(ptr, 1);
+            return false;
+        }
+        sys::lua_getfield(ptr, -1, field.as_ptr());
+        sys::lua_remove(ptr, -2);
+        sys::lua_isnil(ptr, -1) == 0
+    }
+}
+
 pub trait FuncAccess {
     fn try_ptr(&self) -> Result<*mut sys::lua_State, Error>;
 
@@ -31,17 +67,16 @@ pub trait FuncAccess {
     fn try_call<I: ToLua, O: FromLua + ValueArg>(&self, args: I) -> Result<O, Error> {
         unsafe {
             let ptr = self.try_ptr()?;
-            helper::try_check_stack(ptr, I::LEN + O::LEN)?;
+            helper::try_check_stack(ptr, I::LEN + O::LEN + 1)?;
             let _g = StackGuard::new(ptr);
 
+            let base = sys::lua_gettop(ptr);
             self.push_fn(ptr);
-            args.to_lua_unchecked(ptr);
+            args.try_to_lua_unchecked(ptr)?;
 
-            if sys::lua_pcall(ptr, I::LEN, O::LEN, 0) != 0 {
-                Err(Error::from_stack(ptr, -1))
-            } else {
-                O::try_from_lua(ptr, O::LEN * -1)
-            }
+            let nargs = sys::lua_gettop(ptr) - base - 1;
+            helper::pcall(ptr, nargs, helper::nresults::<O>())?;
+            O::try_from_lua(ptr, base + 1)
         }
     }
 
@@ -52,17 +87,71 @@ pub trait FuncAccess {
     ) -> Result<R, Error> {
         unsafe {
             let ptr = self.try_ptr()?;
-            helper::try_check_stack(ptr, I::LEN + O::LEN)?;
+            helper::try_check_stack(ptr, I::LEN + O::LEN + 1)?;
             let _g = StackGuard::new(ptr);
 
+            let base = sys::lua_gettop(ptr);
             self.push_fn(ptr);
-            args.to_lua_unchecked(ptr);
+            args.try_to_lua_unchecked(ptr)?;
 
-            if sys::lua_pcall(ptr, I::LEN, O::LEN, 0) != 0 {
-                Err(Error::from_stack(ptr, -1))
-            } else {
-                O::try_from_lua(ptr, O::LEN * -1).map(|v| f(&v))
+            let nargs = sys::lua_gettop(ptr) - base - 1;
+            helper::pcall(ptr, nargs, helper::nresults::<O>())?;
+            O::try_from_lua(ptr, base + 1).map(|v| f(&v))
+        }
+    }
+
+    fn try_dump(&self, strip: bool) -> Result<Vec<u8>, Error> {
+        unsafe {
+            let ptr = self.try_ptr()?;
+            helper::try_check_stack(ptr, 3)?;
+            let _g = StackGuard::new(ptr);
+
+            if !strip {
+                self.push_fn(ptr);
+                let mut buf = Vec::new();
+                let status = sys::lua_dump_(ptr, dump_writer, &mut buf as *mut Vec<u8> as _);
+                return if status == 0 {
+                    Ok(buf)
+                } else {
+                    Err(Error::UnexpectedType)
+                };
+            }
+
+            if !push_loaded_field(ptr, c"string", c"dump") {
+                return Err(Error::MissingGlobal("string.dump".into()));
+            }
+            self.push_fn(ptr);
+            sys::lua_pushboolean(ptr, 1);
+
+            helper::pcall(ptr, 2, 1)?;
+            Vec::<u8>::try_from_lua(ptr, -1)
+        }
+    }
+
+    fn try_set_env(&self, env: &TableRef) -> Result<(), Error> {
+        unsafe {
+            let ptr = self.try_ptr()?;
+            helper::try_check_stack(ptr, 2)?;
+            let _g = StackGuard::new(ptr);
+
+            self.push_fn(ptr);
+            env.try_to_lua(ptr)?;
+            if sys::lua_setfenv(ptr, -2) == 0 {
+                return Err(Error::UnexpectedType);
             }
+            Ok(())
+        }
+    }
+
+    fn try_get_env(&self) -> Result<TableRef, Error> {
+        unsafe {
+            let ptr = self.try_ptr()?;
+            helper::try_check_stack(ptr, 2)?;
+            let _g = StackGuard::new(ptr);
+
+            self.push_fn(ptr);
+            sys::lua_getfenv(ptr, -1);
+            TableRef::try_from_lua(ptr, -1)
         }
     }
 }
@@ -124,8 +213,53 @@ impl FuncAccess for OwnedState {
     }
 }
 
+#[derive(Debug)]
+pub struct WeakState {
+    slot: WeakSlot,
+    fn_ptr: *const std::ffi::c_void,
+}
+
+impl FuncState for Weak {
+    type State = WeakState;
+}
+
+impl FuncAccess for WeakState {
+    fn try_ptr(&self) -> Result<*mut sys::lua_State, Error> {
+        self.slot.try_with_target(Ok)
+    }
+
+    fn fn_ptr(&self) -> *const std::ffi::c_void {
+        self.fn_ptr
+    }
+
+    fn push_fn(&self, ptr: *mut sys::lua_State) {
+        unsafe { self.slot.push(ptr) };
+    }
+}
+
+type Callback = Box<dyn Fn(*mut sys::lua_State) -> std::ffi::c_int>;
+
+unsafe extern "C-unwind" fn callback_gc(ptr: *mut sys::lua_State) -> i32 {
+    unsafe {
+        let cb_ptr = sys::lua_touserdata(ptr, 1) as *mut *mut Callback;
+        if !cb_ptr.is_null() && !(*cb_ptr).is_null() {
+            std::mem::drop(Box::from_raw(*cb_ptr));
+            *cb_ptr = std::ptr::null_mut();
+        }
+    }
+    0
+}
+
+unsafe extern "C-unwind" fn callback_trampoline(ptr: *mut sys::lua_State) -> std::ffi::c_int {
+    unsafe {
+        let cb_ptr = sys::lua_touserdata(ptr, sys::lua_upvalueindex(1)) as *mut *mut Callback;
+        (**cb_ptr)(ptr)
+    }
+}
+
 pub type StackFn = Func<Borrowed>;
 pub type FnRef = Func<Owned>;
+pub type WeakFnRef = Func<Weak>;
 
 pub struct Func<M>
 where
@@ -140,10 +274,7 @@ where
     M: Mode + FuncState,
     M::State: FuncAccess,
 {
-    pub fn call<I: ToLua, O: FromLua>(&self, args: I) -> Result<O, Error>
-    where
-        O: ValueArg,
-    {
+    pub fn call<I: ToLua, O: FromLua + ValueArg>(&self, args: I) -> Result<O, Error> {
         self.state.try_call(args)
     }
 
@@ -154,6 +285,30 @@ where
     ) -> Result<R, Error> {
         self.state.try_call_then(args, f)
     }
+
+    pub fn try_dump(&self, strip: bool) -> Result<Vec<u8>, Error> {
+        self.state.try_dump(strip)
+    }
+
+    pub fn dump(&self, strip: bool) -> Vec<u8> {
+        self.try_dump(strip).unwrap_display()
+    }
+
+    pub fn try_set_env(&self, env: &TableRef) -> Result<(), Error> {
+        self.state.try_set_env(env)
+    }
+
+    pub fn set_env(&self, env: &TableRef) {
+        self.try_set_env(env).unwrap_display()
+    }
+
+    pub fn try_get_env(&self) -> Result<TableRef, Error> {
+        self.state.try_get_env()
+    }
+
+    pub fn get_env(&self) -> TableRef {
+        self.try_get_env().unwrap_display()
+    }
 }
 
 impl StackFn {
@@ -169,6 +324,82 @@ impl StackFn {
 }
 
 impl FnRef {
+    pub fn try_new<F, A, R>(lua: Rc<InnerLua>, f: F) -> Result<FnRef, Error>
+    where
+        F: Fn(&Lua, A) -> Result<R, Error> + 'static,
+        A: FromLua + ValueArg,
+        R: ToLua,
+    {
+        let callback: Callback = Box::new(move |ptr| unsafe {
+            helper::catch(ptr, || {
+                if A::VARIADIC {
+                    helper::check_min_arg_count(ptr, A::LEN as usize)?;
+                } else {
+                    helper::check_arg_count(ptr, A::LEN as usize)?;
+                }
+                let args = A::try_from_lua(ptr, 1)?;
+                let lua = Lua::from_ptr(ptr);
+                f(&lua, args)
+            })
+        });
+
+        unsafe {
+            let ptr = lua.try_state()?;
+            helper::try_check_stack(ptr, 3)?;
+
+            let size = std::mem::size_of::<*mut Callback>();
+            let cb_ptr = sys::lua_newuserdata(ptr, size) as *mut *mut Callback;
+            *cb_ptr = Box::into_raw(Box::new(callback));
+
+            if sys::luaL_newmetatable(ptr, c"__LJR_CALLBACK".as_ptr()) != 0 {
+                sys::lua_pushcfunction(ptr, callback_gc);
+                sys::lua_setfield(ptr, -2, c"__gc".as_ptr());
+            }
+            sys::lua_setmetatable(ptr, -2);
+
+            sys::lua_pushcclosure(ptr, callback_trampoline, 1);
+            let fn_ptr = sys::lua_topointer(ptr, -1);
+            let id = sys::luaL_ref(ptr, sys::LUA_REGISTRYINDEX);
+            let lua = RefCell::new(lua);
+
+            Ok(FnRef {
+                state: OwnedState { lua, id, fn_ptr },
+            })
+        }
+    }
+
+    pub fn new<F, A, R>(lua: Rc<InnerLua>, f: F) -> FnRef
+    where
+        F: Fn(&Lua, A) -> Result<R, Error> + 'static,
+        A: FromLua + ValueArg,
+        R: ToLua,
+    {
+        Self::try_new(lua, f).unwrap_display()
+    }
+
+    pub fn try_downgrade(&self) -> Result<WeakFnRef, Error> {
+        let lua = self.state.lua.borrow().clone();
+        let ptr = lua.try_state()?;
+        unsafe {
+            helper::try_check_stack(ptr, 1)?;
+            sys::lua_rawgeti(ptr, sys::LUA_REGISTRYINDEX, self.state.id as _);
+            let slot = WeakSlot::try_new(lua, ptr, -1);
+            sys::lua_pop(ptr, 1);
+
+            let fn_ptr = self.state.fn_ptr;
+            Ok(WeakFnRef {
+                state: WeakState {
+                    slot: slot?,
+                    fn_ptr,
+                },
+            })
+        }
+    }
+
+    pub fn downgrade(&self) -> WeakFnRef {
+        self.try_downgrade().unwrap_display()
+    }
+
     pub fn try_clone(&self) -> Result<Self, Error> {
         let lua = self.state.lua.clone();
         let fn_ptr = self.state.fn_ptr;
@@ -183,6 +414,29 @@ impl FnRef {
     }
 }
 
+impl WeakFnRef {
+    pub fn upgrade(&self) -> Option<FnRef> {
+        self.state
+            .slot
+            .try_with_target(|ptr| FnRef::try_from_lua(ptr, -1))
+            .ok()
+    }
+
+    pub fn try_clone(&self) -> Result<WeakFnRef, Error> {
+        let slot = self.state.slot.try_clone()?;
+        let fn_ptr = self.state.fn_ptr;
+        Ok(Self {
+            state: WeakState { slot, fn_ptr },
+        })
+    }
+}
+
+impl Clone for WeakFnRef {
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_display()
+    }
+}
+
 impl Clone for FnRef {
     fn clone(&self) -> Self {
         self.try_clone().unwrap_display()
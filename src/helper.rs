@@ -1,12 +1,17 @@
+use std::cell::RefCell;
+use std::ffi::CStr;
 use std::panic::AssertUnwindSafe;
 
 use crate::Nil;
 use crate::UserData;
 
-use crate::error::Error;
+use crate::error::{Error, Frame};
 use crate::from_lua::FromLua;
+use crate::hook;
 use crate::is_type::IsType;
 use crate::lstr::StackStr;
+use crate::lua::memory;
+use crate::stack_guard::StackGuard;
 use crate::sys;
 use crate::ud::StackUd;
 
@@ -28,6 +33,16 @@ fn raise_error(ptr: *mut sys::lua_State, msg: String) -> ! {
     unsafe { sys::lua_error(ptr) };
 }
 
+fn raise_value(ptr: *mut sys::lua_State, err: Error) -> ! {
+    unsafe {
+        if sys::lua_checkstack(ptr, 3) == 0 {
+            sys::lua_settop(ptr, 0);
+        }
+        err.push(ptr);
+    }
+    unsafe { sys::lua_error(ptr) };
+}
+
 pub fn check_arg_count(ptr: *mut sys::lua_State, expected: usize) -> Result<(), Error> {
     let got = unsafe { crate::sys::lua_gettop(ptr) } as usize;
     if got == expected {
@@ -37,6 +52,46 @@ pub fn check_arg_count(ptr: *mut sys::lua_State, expected: usize) -> Result<(),
     }
 }
 
+/// # Safety
+///
+/// `ptr` must be a valid Lua state.
+pub unsafe fn check_min_arg_count(ptr: *mut sys::lua_State, expected: usize) -> Result<(), Error> {
+    let got = unsafe { crate::sys::lua_gettop(ptr) } as usize;
+    if got >= expected {
+        Ok(())
+    } else {
+        Err(Error::ArgumentCountMismatch(expected, got))
+    }
+}
+
+/// # Safety
+///
+/// `ptr` must be a valid Lua state.
+pub unsafe fn check_arg_range(
+    ptr: *mut sys::lua_State,
+    min: usize,
+    max: Option<usize>,
+) -> Result<(), Error> {
+    let got = unsafe { crate::sys::lua_gettop(ptr) } as usize;
+    match max {
+        _ if got < min => Err(Error::ArgumentCountMismatch(min, got)),
+        Some(max) if got > max => Err(Error::ArgumentCountMismatch(max, got)),
+        _ => Ok(()),
+    }
+}
+
+fn is_none_or_nil(ptr: *mut sys::lua_State, idx: i32) -> bool {
+    unsafe { sys::lua_type(ptr, idx) <= sys::LUA_TNIL }
+}
+
+pub(crate) fn nresults<T: FromLua>() -> i32 {
+    if T::VARIADIC {
+        sys::LUA_MULTRET
+    } else {
+        T::LEN
+    }
+}
+
 pub fn from_lua<T: crate::from_lua::FromLua>(
     ptr: *mut sys::lua_State,
     idx: &mut i32,
@@ -61,7 +116,8 @@ pub fn from_lua_opt<T: FromLua>(
             Ok(Some(value))
         }
         Err(_) => {
-            if Nil::is_type(ptr, *idx) {
+            if is_none_or_nil(ptr, *idx) {
+                *idx += T::len();
                 Ok(None)
             } else {
                 Err(Error::ArgumentTypeMismatch(
@@ -83,7 +139,8 @@ pub fn from_lua_opt_str(
             Ok(Some(value))
         }
         Err(_) => {
-            if Nil::is_type(ptr, *idx) {
+            if is_none_or_nil(ptr, *idx) {
+                *idx += StackStr::len();
                 Ok(None)
             } else {
                 Err(Error::ArgumentTypeMismatch(*idx as _, "&str or nil".into()))
@@ -105,7 +162,8 @@ where
             Ok(Some(value))
         }
         Err(_) => {
-            if Nil::is_type(ptr, *idx) {
+            if is_none_or_nil(ptr, *idx) {
+                *idx += <StackUd<T> as crate::from_lua::FromLua>::len();
                 Ok(None)
             } else {
                 Err(Error::ArgumentTypeMismatch(
@@ -133,21 +191,45 @@ where
     }
 }
 
-pub fn catch<F, R>(ptr: *mut sys::lua_State, f: F) -> std::ffi::c_int
+/// Moves the userdata operand of a commutative metamethod (`__add`, `__mul`) to
+/// index 1, so `1 + ud` reaches the same method as `ud + 1`.
+///
+/// # Safety
+///
+/// `ptr` must be a valid Lua state with both operands at indices 1 and 2.
+pub unsafe fn swap_operands<T: UserData>(ptr: *mut sys::lua_State) {
+    if StackUd::<T>::try_from_lua(ptr, 1).is_err() && StackUd::<T>::try_from_lua(ptr, 2).is_ok() {
+        unsafe {
+            sys::lua_pushvalue(ptr, 2);
+            sys::lua_remove(ptr, 2);
+            sys::lua_insert(ptr, 1);
+        }
+    }
+}
+
+/// Runs `f` and pushes its result, turning errors and panics into Lua errors.
+///
+/// # Safety
+///
+/// `ptr` must be the state of the running C function, since errors longjmp out
+/// of it through `lua_error`.
+pub unsafe fn catch<F, R>(ptr: *mut sys::lua_State, f: F) -> std::ffi::c_int
 where
     F: FnOnce() -> Result<R, Error>,
     R: crate::to_lua::ToLua,
 {
-    let result: Result<std::ffi::c_int, String> = {
-        let result = std::panic::catch_unwind(AssertUnwindSafe(f));
+    let enforced = unsafe { memory::set_enforced(ptr, false) };
+    let result: Result<std::ffi::c_int, Result<Error, String>> = {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let r = f()?;
+            let top = unsafe { sys::lua_gettop(ptr) };
+            let g = StackGuard::new(ptr);
+            crate::to_lua::ToLua::try_to_lua(r, ptr)?;
+            g.commit();
+            Ok(unsafe { sys::lua_gettop(ptr) } - top)
+        }));
         match result {
-            Ok(r) => match r {
-                Ok(r) => {
-                    crate::to_lua::ToLua::to_lua(r, ptr);
-                    Ok(<R as crate::to_lua::ToLua>::len() as _)
-                }
-                Err(msg) => Err(msg.to_string()),
-            },
+            Ok(r) => r.map_err(Ok),
             Err(e) => {
                 let msg = {
                     let err_msg = if let Some(s) = e.downcast_ref::<String>() {
@@ -159,14 +241,41 @@ where
                     };
                     format!("Rust panic: {}", err_msg)
                 };
-                Err(msg)
+                Err(Err(msg))
             }
         }
     };
 
+    unsafe { memory::set_enforced(ptr, enforced) };
     match result {
         Ok(n) => n,
-        Err(msg) => raise_error(ptr, msg),
+        Err(Ok(err)) => raise_value(ptr, err),
+        Err(Err(msg)) => raise_error(ptr, msg),
+    }
+}
+
+pub(crate) unsafe fn push_lib(
+    ptr: *mut sys::lua_State,
+    name: &CStr,
+    open: sys::lua_CFunction,
+) -> Result<(), Error> {
+    unsafe {
+        try_check_stack(ptr, 3)?;
+        sys::luaL_findtable(ptr, sys::LUA_REGISTRYINDEX, c"_LOADED".as_ptr(), 1);
+        sys::lua_getfield(ptr, -1, name.as_ptr());
+
+        if sys::lua_istable(ptr, -1) == 0 {
+            sys::lua_pop(ptr, 1);
+            sys::lua_pushcfunction(ptr, open);
+            if sys::lua_pcall(ptr, 0, 1, 0) != 0 {
+                let err = Error::from_stack(ptr, -1);
+                sys::lua_pop(ptr, 2);
+                return Err(err);
+            }
+        }
+
+        sys::lua_remove(ptr, -2);
+        Ok(())
     }
 }
 
@@ -180,3 +289,156 @@ pub unsafe fn try_check_stack(ptr: *mut sys::lua_State, len: i32) -> Result<(),
         }
     }
 }
+
+/// # Safety
+///
+/// `ptr` must be a valid Lua state with the target table on top of the stack.
+pub unsafe fn try_set_field<T: crate::to_lua::ToLua>(
+    ptr: *mut sys::lua_State,
+    key: &str,
+    value: T,
+) -> Result<(), Error> {
+    unsafe {
+        try_check_stack(ptr, 1)?;
+        sys::lua_pushlstring_(ptr, key.as_ptr() as _, key.len());
+        if let Err(e) = value.try_to_lua(ptr) {
+            sys::lua_pop(ptr, 1);
+            return Err(e.in_field(key));
+        }
+        sys::lua_rawset(ptr, -3);
+    }
+    Ok(())
+}
+
+/// # Safety
+///
+/// `ptr` must be a valid Lua state with the target table on top of the stack.
+pub unsafe fn try_set_index<T: crate::to_lua::ToLua>(
+    ptr: *mut sys::lua_State,
+    index: i32,
+    value: T,
+) -> Result<(), Error> {
+    unsafe {
+        value
+            .try_to_lua(ptr)
+            .map_err(|e| e.in_field(&index.to_string()))?;
+        sys::lua_rawseti_(ptr, -2, index as _);
+    }
+    Ok(())
+}
+
+/// # Safety
+///
+/// `ptr` must be a valid Lua state and `idx` the absolute index of a table.
+pub unsafe fn try_get_field<T: FromLua>(
+    ptr: *mut sys::lua_State,
+    idx: i32,
+    key: &str,
+    default: Option<fn() -> T>,
+) -> Result<T, Error> {
+    unsafe {
+        try_check_stack(ptr, 2)?;
+        sys::lua_pushlstring_(ptr, key.as_ptr() as _, key.len());
+        sys::lua_rawget(ptr, idx);
+        let value = match default {
+            Some(default) if Nil::is_type(ptr, -1) => Ok(default()),
+            _ => T::try_from_lua(ptr, -1).map_err(|e| e.in_field(key)),
+        };
+        sys::lua_pop(ptr, 1);
+        value
+    }
+}
+
+/// # Safety
+///
+/// `ptr` must be a valid Lua state and `idx` the absolute index of a table.
+pub unsafe fn try_get_index<T: FromLua>(
+    ptr: *mut sys::lua_State,
+    idx: i32,
+    index: i32,
+    default: Option<fn() -> T>,
+) -> Result<T, Error> {
+    unsafe {
+        try_check_stack(ptr, 1)?;
+        sys::lua_rawgeti_(ptr, idx, index as _);
+        let value = match default {
+            Some(default) if Nil::is_type(ptr, -1) => Ok(default()),
+            _ => T::try_from_lua(ptr, -1).map_err(|e| e.in_field(&index.to_string())),
+        };
+        sys::lua_pop(ptr, 1);
+        value
+    }
+}
+
+const MAX_TRACEBACK_FRAMES: i32 = 64;
+
+thread_local! {
+    static TRACEBACK: RefCell<Vec<Frame>> = const { RefCell::new(Vec::new()) };
+}
+
+pub(crate) unsafe fn collect_frames(ptr: *mut sys::lua_State, level: i32) -> Vec<Frame> {
+    let mut frames = vec![];
+    unsafe {
+        let mut ar: sys::lua_Debug = std::mem::zeroed();
+        let mut level = level;
+        while frames.len() < MAX_TRACEBACK_FRAMES as usize
+            && sys::lua_getstack(ptr, level, &mut ar) != 0
+        {
+            sys::lua_getinfo(ptr, c"Snl".as_ptr(), &mut ar);
+            let source = CStr::from_ptr(ar.short_src.as_ptr())
+                .to_string_lossy()
+                .to_string();
+            let name = if ar.name.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(ar.name).to_string_lossy().to_string())
+            };
+            frames.push(Frame {
+                source,
+                line: u32::try_from(ar.currentline).ok(),
+                name,
+            });
+            level += 1;
+        }
+    }
+    frames
+}
+
+unsafe extern "C-unwind" fn traceback_handler(ptr: *mut sys::lua_State) -> std::ffi::c_int {
+    let frames = unsafe { collect_frames(ptr, 1) };
+    TRACEBACK.with(|t| *t.borrow_mut() = frames);
+    1
+}
+
+pub(crate) unsafe fn pcall(
+    ptr: *mut sys::lua_State,
+    nargs: i32,
+    nresults: i32,
+) -> Result<(), Error> {
+    unsafe {
+        try_check_stack(ptr, 1)?;
+        let base = sys::lua_gettop(ptr) - nargs;
+        sys::lua_pushcfunction(ptr, traceback_handler);
+        sys::lua_insert(ptr, base);
+
+        TRACEBACK.with(|t| t.borrow_mut().clear());
+        let enforced = memory::set_enforced(ptr, true);
+        hook::enter(ptr);
+        let status = sys::lua_pcall(ptr, nargs, nresults, base);
+        hook::leave(ptr);
+        memory::set_enforced(ptr, enforced);
+        if status != 0 {
+            let frames = TRACEBACK.with(|t| std::mem::take(&mut *t.borrow_mut()));
+            let err = if memory::limit_exceeded(ptr, status) {
+                Error::MemoryLimitExceeded
+            } else {
+                Error::from_stack(ptr, -1).with_traceback(frames)
+            };
+            sys::lua_settop(ptr, base - 1);
+            Err(err)
+        } else {
+            sys::lua_remove(ptr, base);
+            Ok(())
+        }
+    }
+}
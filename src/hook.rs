@@ -0,0 +1,289 @@
+use std::{
+    cell::Cell,
+    ffi::c_int,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::{error::Error, helper, jit, lua::Lua, sys};
+
+const HOOK_KEY: usize = 0x6C6A72_06;
+
+const EXECUTION_LIMIT_STEP: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HookTriggers {
+    pub every_nth_instruction: Option<u32>,
+    pub on_calls: bool,
+    pub on_returns: bool,
+    pub every_line: bool,
+}
+
+impl HookTriggers {
+    fn mask(&self) -> (c_int, c_int) {
+        let mut mask = 0;
+        let mut count = 0;
+        if let Some(n) = self.every_nth_instruction {
+            mask |= sys::LUA_MASKCOUNT;
+            count = n.clamp(1, c_int::MAX as u32) as c_int;
+        }
+        if self.on_calls {
+            mask |= sys::LUA_MASKCALL;
+        }
+        if self.on_returns {
+            mask |= sys::LUA_MASKRET;
+        }
+        if self.every_line {
+            mask |= sys::LUA_MASKLINE;
+        }
+        (mask, count)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Call,
+    Return,
+    Line(i32),
+    Count,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionLimit {
+    Instructions(u64),
+    Duration(Duration),
+}
+
+impl From<u64> for ExecutionLimit {
+    fn from(value: u64) -> Self {
+        ExecutionLimit::Instructions(value)
+    }
+}
+
+impl From<Duration> for ExecutionLimit {
+    fn from(value: Duration) -> Self {
+        ExecutionLimit::Duration(value)
+    }
+}
+
+pub(crate) type HookCallback = Box<dyn Fn(&Lua, HookEvent) -> Result<(), Error>>;
+pub(crate) type HookReset = Box<dyn Fn()>;
+
+struct HookState {
+    callback: HookCallback,
+    reset: Option<HookReset>,
+    mask: c_int,
+    count: c_int,
+    depth: Cell<u32>,
+    suspend_jit: bool,
+}
+
+unsafe extern "C-unwind" fn hook_gc(ptr: *mut sys::lua_State) -> i32 {
+    unsafe {
+        let state_ptr = sys::lua_touserdata(ptr, 1) as *mut *mut HookState;
+        if !state_ptr.is_null() && !(*state_ptr).is_null() {
+            std::mem::drop(Box::from_raw(*state_ptr));
+            *state_ptr = std::ptr::null_mut();
+        }
+    }
+    0
+}
+
+unsafe fn push_hook_state(ptr: *mut sys::lua_State) -> *mut HookState {
+    unsafe {
+        sys::lua_pushlightuserdata(ptr, HOOK_KEY as *mut std::ffi::c_void);
+        sys::lua_rawget(ptr, sys::LUA_REGISTRYINDEX);
+        let state_ptr = sys::lua_touserdata(ptr, -1) as *mut *mut HookState;
+        if state_ptr.is_null() {
+            std::ptr::null_mut()
+        } else {
+            *state_ptr
+        }
+    }
+}
+
+unsafe extern "C-unwind" fn hook_trampoline(ptr: *mut sys::lua_State, ar: *mut sys::lua_Debug) {
+    unsafe {
+        if sys::lua_checkstack(ptr, 2) == 0 {
+            return;
+        }
+
+        let state = push_hook_state(ptr);
+        if state.is_null() {
+            sys::lua_pop(ptr, 1);
+            return;
+        }
+
+        let event = match (*ar).event {
+            sys::LUA_HOOKCALL => HookEvent::Call,
+            sys::LUA_HOOKLINE => HookEvent::Line((*ar).currentline),
+            sys::LUA_HOOKCOUNT => HookEvent::Count,
+            _ => HookEvent::Return,
+        };
+
+        let callback = &(*state).callback;
+        helper::catch(ptr, || {
+            let result = callback(&Lua::from_ptr(ptr), event);
+            if let Err(Error::ExecutionLimitExceeded) = result {
+                let mask = sys::lua_gethookmask(ptr) | sys::LUA_MASKCOUNT;
+                sys::lua_sethook(ptr, Some(hook_trampoline), mask, 1);
+            }
+            result
+        });
+        sys::lua_pop(ptr, 1);
+    }
+}
+
+pub(crate) unsafe fn enter(ptr: *mut sys::lua_State) {
+    unsafe {
+        if sys::lua_checkstack(ptr, 2) == 0 {
+            return;
+        }
+        let state = push_hook_state(ptr);
+        sys::lua_pop(ptr, 1);
+        if state.is_null() {
+            return;
+        }
+
+        let depth = (*state).depth.get();
+        (*state).depth.set(depth + 1);
+        if depth == 0 {
+            if let Some(reset) = &(*state).reset {
+                reset();
+            }
+            if (*state).suspend_jit {
+                jit::set_enabled(ptr, false);
+            }
+            sys::lua_sethook(ptr, Some(hook_trampoline), (*state).mask, (*state).count);
+        }
+    }
+}
+
+pub(crate) unsafe fn leave(ptr: *mut sys::lua_State) {
+    unsafe {
+        if sys::lua_checkstack(ptr, 2) == 0 {
+            return;
+        }
+        let state = push_hook_state(ptr);
+        sys::lua_pop(ptr, 1);
+        if !state.is_null() {
+            let depth = (*state).depth.get();
+            (*state).depth.set(depth.saturating_sub(1));
+        }
+    }
+}
+
+pub(crate) unsafe fn set_hook(
+    ptr: *mut sys::lua_State,
+    triggers: HookTriggers,
+    callback: HookCallback,
+    reset: Option<HookReset>,
+) -> Result<(), Error> {
+    unsafe {
+        remove_hook(ptr)?;
+
+        let (mask, count) = triggers.mask();
+        let suspend_jit = mask & (sys::LUA_MASKCOUNT | sys::LUA_MASKLINE) != 0;
+        if suspend_jit {
+            jit::set_enabled(ptr, false);
+        }
+
+        helper::try_check_stack(ptr, 3)?;
+        sys::lua_pushlightuserdata(ptr, HOOK_KEY as *mut std::ffi::c_void);
+        let size = std::mem::size_of::<*mut HookState>();
+        let state_ptr = sys::lua_newuserdata(ptr, size) as *mut *mut HookState;
+        *state_ptr = Box::into_raw(Box::new(HookState {
+            callback,
+            reset,
+            mask,
+            count,
+            depth: Cell::new(0),
+            suspend_jit,
+        }));
+
+        if sys::luaL_newmetatable(ptr, c"__LJR_HOOK".as_ptr()) != 0 {
+            sys::lua_pushcfunction(ptr, hook_gc);
+            sys::lua_setfield(ptr, -2, c"__gc".as_ptr());
+        }
+        sys::lua_setmetatable(ptr, -2);
+        sys::lua_rawset(ptr, sys::LUA_REGISTRYINDEX);
+
+        sys::lua_sethook(ptr, Some(hook_trampoline), mask, count);
+    }
+    Ok(())
+}
+
+/// Whether the installed hook keeps the JIT off, since compiled traces skip
+/// count and line hooks.
+pub(crate) unsafe fn suspends_jit(ptr: *mut sys::lua_State) -> bool {
+    unsafe {
+        if sys::lua_checkstack(ptr, 2) == 0 {
+            return false;
+        }
+        let state = push_hook_state(ptr);
+        sys::lua_pop(ptr, 1);
+        !state.is_null() && (*state).suspend_jit
+    }
+}
+
+pub(crate) unsafe fn remove_hook(ptr: *mut sys::lua_State) -> Result<(), Error> {
+    unsafe {
+        helper::try_check_stack(ptr, 2)?;
+        let state = push_hook_state(ptr);
+        sys::lua_pop(ptr, 1);
+        if state.is_null() {
+            return Ok(());
+        }
+
+        sys::lua_sethook(ptr, None, 0, 0);
+        if (*state).suspend_jit && jit::is_enabled(ptr)? {
+            jit::set_enabled(ptr, true);
+        }
+
+        sys::lua_pushlightuserdata(ptr, HOOK_KEY as *mut std::ffi::c_void);
+        sys::lua_pushnil(ptr);
+        sys::lua_rawset(ptr, sys::LUA_REGISTRYINDEX);
+    }
+    Ok(())
+}
+
+pub(crate) fn execution_limit(limit: ExecutionLimit) -> (HookTriggers, HookCallback, HookReset) {
+    match limit {
+        ExecutionLimit::Instructions(max) => {
+            let step = max.clamp(1, EXECUTION_LIMIT_STEP as u64) as u32;
+            let used = Rc::new(Cell::new(0u64));
+            let triggers = HookTriggers {
+                every_nth_instruction: Some(step),
+                ..Default::default()
+            };
+            let counter = used.clone();
+            let callback: HookCallback = Box::new(move |_, _| {
+                counter.set(counter.get() + step as u64);
+                if counter.get() >= max {
+                    Err(Error::ExecutionLimitExceeded)
+                } else {
+                    Ok(())
+                }
+            });
+            let reset: HookReset = Box::new(move || used.set(0));
+            (triggers, callback, reset)
+        }
+        ExecutionLimit::Duration(duration) => {
+            let deadline = Rc::new(Cell::new(Instant::now() + duration));
+            let triggers = HookTriggers {
+                every_nth_instruction: Some(EXECUTION_LIMIT_STEP),
+                ..Default::default()
+            };
+            let current = deadline.clone();
+            let callback: HookCallback = Box::new(move |_, _| {
+                if Instant::now() >= current.get() {
+                    Err(Error::ExecutionLimitExceeded)
+                } else {
+                    Ok(())
+                }
+            });
+            let reset: HookReset = Box::new(move || deadline.set(Instant::now() + duration));
+            (triggers, callback, reset)
+        }
+    }
+}
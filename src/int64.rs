@@ -0,0 +1,197 @@
+use crate::{
+    error::Error, from_lua::FromLua, helper, is_type::IsType, stack_guard::StackGuard, sys,
+    to_lua::ToLua,
+};
+
+pub(crate) const LUA_TCDATA: i32 = 10;
+
+const INT64_KIND_KEY: usize = 0x6C6A72_03;
+const INT64_NEW_KEY: usize = 0x6C6A72_04;
+
+const INT64_HELPERS: &std::ffi::CStr = c"
+local ffi = ...
+local i64, u64 = ffi.typeof('int64_t'), ffi.typeof('uint64_t')
+return function(v)
+    if ffi.istype(i64, v) then return 1 end
+    if ffi.istype(u64, v) then return 2 end
+    return 0
+end, function(unsigned)
+    if unsigned then return u64() end
+    return i64()
+end";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct BoxedI64(pub i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct BoxedU64(pub u64);
+
+impl From<i64> for BoxedI64 {
+    fn from(value: i64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<BoxedI64> for i64 {
+    fn from(value: BoxedI64) -> Self {
+        value.0
+    }
+}
+
+impl From<u64> for BoxedU64 {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<BoxedU64> for u64 {
+    fn from(value: BoxedU64) -> Self {
+        value.0
+    }
+}
+
+pub(crate) unsafe fn push_ffi(ptr: *mut sys::lua_State) -> Result<(), Error> {
+    unsafe { helper::push_lib(ptr, c"ffi", sys::luaopen_ffi) }
+}
+
+unsafe fn push_helper(ptr: *mut sys::lua_State, key: usize) -> Result<(), Error> {
+    unsafe {
+        helper::try_check_stack(ptr, 4)?;
+        sys::lua_pushlightuserdata(ptr, key as *mut std::ffi::c_void);
+        sys::lua_rawget(ptr, sys::LUA_REGISTRYINDEX);
+        if sys::lua_isfunction(ptr, -1) != 0 {
+            return Ok(());
+        }
+        sys::lua_pop(ptr, 1);
+
+        let g = StackGuard::new(ptr);
+        if sys::luaL_loadstring(ptr, INT64_HELPERS.as_ptr()) != 0 {
+            return Err(Error::from_stack(ptr, -1));
+        }
+        push_ffi(ptr)?;
+        if sys::lua_pcall(ptr, 1, 2, 0) != 0 {
+            return Err(Error::from_stack(ptr, -1));
+        }
+
+        sys::lua_pushlightuserdata(ptr, INT64_KIND_KEY as *mut std::ffi::c_void);
+        sys::lua_pushvalue(ptr, -3);
+        sys::lua_rawset(ptr, sys::LUA_REGISTRYINDEX);
+
+        sys::lua_pushlightuserdata(ptr, INT64_NEW_KEY as *mut std::ffi::c_void);
+        sys::lua_pushvalue(ptr, -2);
+        sys::lua_rawset(ptr, sys::LUA_REGISTRYINDEX);
+
+        if key == INT64_KIND_KEY {
+            sys::lua_pop(ptr, 1);
+        } else {
+            sys::lua_remove(ptr, -2);
+        }
+
+        g.commit();
+        Ok(())
+    }
+}
+
+unsafe fn boxed_kind(ptr: *mut sys::lua_State, idx: i32) -> Result<i32, Error> {
+    unsafe {
+        if sys::lua_type(ptr, idx) != LUA_TCDATA {
+            return Ok(0);
+        }
+
+        let idx = sys::lua_absindex(ptr, idx);
+        let _g = StackGuard::new(ptr);
+        push_helper(ptr, INT64_KIND_KEY)?;
+        sys::lua_pushvalue(ptr, idx);
+        if sys::lua_pcall(ptr, 1, 1, 0) != 0 {
+            return Err(Error::from_stack(ptr, -1));
+        }
+
+        Ok(sys::lua_tointeger(ptr, -1) as i32)
+    }
+}
+
+unsafe fn push_boxed(ptr: *mut sys::lua_State, unsigned: bool, bits: u64) -> Result<(), Error> {
+    unsafe {
+        let g = StackGuard::new(ptr);
+        push_helper(ptr, INT64_NEW_KEY)?;
+        sys::lua_pushboolean(ptr, unsigned as _);
+        if sys::lua_pcall(ptr, 1, 1, 0) != 0 {
+            return Err(Error::from_stack(ptr, -1));
+        }
+
+        let data = sys::lua_topointer(ptr, -1) as *mut u64;
+        *data = bits;
+        g.commit();
+        Ok(())
+    }
+}
+
+unsafe fn read_boxed(ptr: *mut sys::lua_State, idx: i32) -> u64 {
+    unsafe { *(sys::lua_topointer(ptr, idx) as *const u64) }
+}
+
+unsafe impl FromLua for BoxedI64 {
+    fn try_from_lua(ptr: *mut sys::lua_State, idx: i32) -> Result<Self, Error> {
+        match unsafe { boxed_kind(ptr, idx)? } {
+            1 => Ok(BoxedI64(unsafe { read_boxed(ptr, idx) } as i64)),
+            2 => {
+                let value = unsafe { read_boxed(ptr, idx) };
+                i64::try_from(value)
+                    .map(BoxedI64)
+                    .map_err(|_| Error::InvalidNumberConversion(value.to_string(), "i64"))
+            }
+            _ => i64::try_from_lua(ptr, idx).map(BoxedI64),
+        }
+    }
+}
+
+unsafe impl FromLua for BoxedU64 {
+    fn try_from_lua(ptr: *mut sys::lua_State, idx: i32) -> Result<Self, Error> {
+        match unsafe { boxed_kind(ptr, idx)? } {
+            1 => {
+                let value = unsafe { read_boxed(ptr, idx) } as i64;
+                u64::try_from(value)
+                    .map(BoxedU64)
+                    .map_err(|_| Error::InvalidNumberConversion(value.to_string(), "u64"))
+            }
+            2 => Ok(BoxedU64(unsafe { read_boxed(ptr, idx) })),
+            _ => u64::try_from_lua(ptr, idx).map(BoxedU64),
+        }
+    }
+}
+
+unsafe impl ToLua for &BoxedI64 {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        unsafe { push_boxed(ptr, false, self.0 as u64) }
+    }
+}
+
+unsafe impl ToLua for BoxedI64 {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        unsafe { (&self).try_to_lua_unchecked(ptr) }
+    }
+}
+
+unsafe impl ToLua for &BoxedU64 {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        unsafe { push_boxed(ptr, true, self.0) }
+    }
+}
+
+unsafe impl ToLua for BoxedU64 {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        unsafe { (&self).try_to_lua_unchecked(ptr) }
+    }
+}
+
+impl IsType for BoxedI64 {
+    fn is_type(ptr: *mut sys::lua_State, idx: i32) -> bool {
+        BoxedI64::try_from_lua(ptr, idx).is_ok()
+    }
+}
+
+impl IsType for BoxedU64 {
+    fn is_type(ptr: *mut sys::lua_State, idx: i32) -> bool {
+        BoxedU64::try_from_lua(ptr, idx).is_ok()
+    }
+}
@@ -1,4 +1,4 @@
-use crate::sys;
+use crate::{from_lua::integer_from_number, sys};
 
 use crate::{AnyLuaFunction, AnyNativeFunction, AnyUserData, Coroutine, LightUserData, Nil};
 
@@ -6,12 +6,23 @@ pub trait IsType {
     fn is_type(ptr: *mut sys::lua_State, idx: i32) -> bool;
 }
 
-impl IsType for i32 {
-    fn is_type(ptr: *mut crate::sys::lua_State, idx: i32) -> bool {
-        unsafe { sys::lua_isnumber(ptr, idx) != 0 }
-    }
+macro_rules! impl_is_type_int {
+    ($($ty:ty),*) => {
+        $(
+            impl IsType for $ty {
+                fn is_type(ptr: *mut crate::sys::lua_State, idx: i32) -> bool {
+                    unsafe {
+                        sys::lua_isnumber(ptr, idx) != 0
+                            && integer_from_number::<$ty>(sys::lua_tonumber(ptr, idx)).is_some()
+                    }
+                }
+            }
+        )*
+    };
 }
 
+impl_is_type_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
 impl IsType for f32 {
     fn is_type(ptr: *mut crate::sys::lua_State, idx: i32) -> bool {
         unsafe { sys::lua_isnumber(ptr, idx) != 0 }
@@ -0,0 +1,209 @@
+use std::{ffi::c_int, rc::Rc};
+
+use crate::{
+    error::{Error, UnwrapDisplay},
+    from_lua::FromLua,
+    func::FnRef,
+    helper, hook,
+    lua::InnerLua,
+    stack_guard::StackGuard,
+    sys,
+    to_lua::ToLua,
+};
+
+pub(crate) const LUAJIT_MODE_ENGINE: c_int = 0;
+pub(crate) const LUAJIT_MODE_FUNC: c_int = 2;
+
+pub(crate) const LUAJIT_MODE_OFF: c_int = 0x0000;
+pub(crate) const LUAJIT_MODE_ON: c_int = 0x0100;
+pub(crate) const LUAJIT_MODE_FLUSH: c_int = 0x0200;
+
+unsafe extern "C-unwind" {
+    pub(crate) fn luaJIT_setmode(L: *mut sys::lua_State, idx: c_int, mode: c_int) -> c_int;
+}
+
+const JIT_MODE_KEY: usize = 0x6C6A72_08;
+
+/// Engine mode last requested through ljr. LuaJIT cannot report it from C and
+/// `jit.status` belongs to scripts, so the mode is tracked on our side. The JIT
+/// stays off until `luaopen_jit` runs.
+pub(crate) unsafe fn is_enabled(ptr: *mut sys::lua_State) -> Result<bool, Error> {
+    unsafe {
+        helper::try_check_stack(ptr, 1)?;
+        sys::lua_pushlightuserdata(ptr, JIT_MODE_KEY as *mut std::ffi::c_void);
+        sys::lua_rawget(ptr, sys::LUA_REGISTRYINDEX);
+        let enabled = sys::lua_toboolean(ptr, -1) != 0;
+        sys::lua_pop(ptr, 1);
+        Ok(enabled)
+    }
+}
+
+pub(crate) unsafe fn record_enabled(ptr: *mut sys::lua_State, enabled: bool) -> Result<(), Error> {
+    unsafe {
+        helper::try_check_stack(ptr, 2)?;
+        sys::lua_pushlightuserdata(ptr, JIT_MODE_KEY as *mut std::ffi::c_void);
+        sys::lua_pushboolean(ptr, enabled as c_int);
+        sys::lua_rawset(ptr, sys::LUA_REGISTRYINDEX);
+        Ok(())
+    }
+}
+
+pub(crate) unsafe fn set_enabled(ptr: *mut sys::lua_State, enabled: bool) -> bool {
+    unsafe {
+        if enabled {
+            luaJIT_setmode(ptr, 0, LUAJIT_MODE_ENGINE | LUAJIT_MODE_ON) != 0
+        } else {
+            luaJIT_setmode(ptr, 0, LUAJIT_MODE_ENGINE | LUAJIT_MODE_OFF) != 0
+                && luaJIT_setmode(ptr, 0, LUAJIT_MODE_ENGINE | LUAJIT_MODE_FLUSH) != 0
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JitStatus {
+    pub enabled: bool,
+    pub flags: Vec<String>,
+}
+
+pub struct Jit {
+    lua: Rc<InnerLua>,
+}
+
+impl Jit {
+    pub(crate) fn new(lua: Rc<InnerLua>) -> Self {
+        Self { lua }
+    }
+
+    fn try_engine_mode(&self, mode: c_int) -> Result<(), Error> {
+        let ptr = self.lua.try_state()?;
+        if unsafe { luaJIT_setmode(ptr, 0, LUAJIT_MODE_ENGINE | mode) } != 0 {
+            Ok(())
+        } else {
+            Err(Error::Generic("failed to change jit mode".into()))
+        }
+    }
+
+    fn try_function_mode(&self, func: &FnRef, mode: c_int) -> Result<(), Error> {
+        let ptr = self.lua.try_state()?;
+        unsafe {
+            helper::try_check_stack(ptr, 1)?;
+            let _g = StackGuard::new(ptr);
+            func.try_to_lua_unchecked(ptr)?;
+            if luaJIT_setmode(ptr, -1, LUAJIT_MODE_FUNC | mode) != 0 {
+                Ok(())
+            } else {
+                Err(Error::UnexpectedType)
+            }
+        }
+    }
+
+    fn try_opt_start<T: ToLua>(&self, arg: T) -> Result<(), Error> {
+        let ptr = self.lua.try_state()?;
+        unsafe {
+            helper::try_check_stack(ptr, 3)?;
+            let _g = StackGuard::new(ptr);
+            helper::push_lib(ptr, c"jit", sys::luaopen_jit)?;
+            sys::lua_getfield(ptr, -1, c"opt".as_ptr());
+            sys::lua_getfield(ptr, -1, c"start".as_ptr());
+            arg.try_to_lua_unchecked(ptr)?;
+            helper::pcall(ptr, 1, 0)
+        }
+    }
+
+    pub fn try_set_enabled(&self, enabled: bool) -> Result<(), Error> {
+        let ptr = self.lua.try_state()?;
+        unsafe { record_enabled(ptr, enabled)? };
+        if enabled && unsafe { hook::suspends_jit(ptr) } {
+            return Ok(());
+        }
+        self.try_engine_mode(if enabled {
+            LUAJIT_MODE_ON
+        } else {
+            LUAJIT_MODE_OFF
+        })
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.try_set_enabled(enabled).unwrap_display()
+    }
+
+    pub fn try_flush(&self) -> Result<(), Error> {
+        self.try_engine_mode(LUAJIT_MODE_FLUSH)
+    }
+
+    pub fn flush(&self) {
+        self.try_flush().unwrap_display()
+    }
+
+    pub fn try_set_function_enabled(&self, func: &FnRef, enabled: bool) -> Result<(), Error> {
+        self.try_function_mode(
+            func,
+            if enabled {
+                LUAJIT_MODE_ON
+            } else {
+                LUAJIT_MODE_OFF
+            },
+        )
+    }
+
+    pub fn set_function_enabled(&self, func: &FnRef, enabled: bool) {
+        self.try_set_function_enabled(func, enabled)
+            .unwrap_display()
+    }
+
+    pub fn try_flush_function(&self, func: &FnRef) -> Result<(), Error> {
+        self.try_function_mode(func, LUAJIT_MODE_FLUSH)
+    }
+
+    pub fn flush_function(&self, func: &FnRef) {
+        self.try_flush_function(func).unwrap_display()
+    }
+
+    pub fn try_set_opt_level(&self, level: u8) -> Result<(), Error> {
+        self.try_opt_start(level)
+    }
+
+    pub fn set_opt_level(&self, level: u8) {
+        self.try_set_opt_level(level).unwrap_display()
+    }
+
+    pub fn try_set_opt_flag(&self, flag: &str, enabled: bool) -> Result<(), Error> {
+        let sign = if enabled { '+' } else { '-' };
+        self.try_opt_start(format!("{sign}{flag}"))
+    }
+
+    pub fn set_opt_flag(&self, flag: &str, enabled: bool) {
+        self.try_set_opt_flag(flag, enabled).unwrap_display()
+    }
+
+    pub fn try_set_opt_param(&self, param: &str, value: i32) -> Result<(), Error> {
+        self.try_opt_start(format!("{param}={value}"))
+    }
+
+    pub fn set_opt_param(&self, param: &str, value: i32) {
+        self.try_set_opt_param(param, value).unwrap_display()
+    }
+
+    pub fn try_status(&self) -> Result<JitStatus, Error> {
+        let ptr = self.lua.try_state()?;
+        unsafe {
+            helper::try_check_stack(ptr, 2)?;
+            let _g = StackGuard::new(ptr);
+            helper::push_lib(ptr, c"jit", sys::luaopen_jit)?;
+            let base = sys::lua_gettop(ptr);
+            sys::lua_getfield(ptr, -1, c"status".as_ptr());
+            helper::pcall(ptr, 0, sys::LUA_MULTRET)?;
+
+            let enabled = sys::lua_toboolean(ptr, base + 1) != 0;
+            let mut flags = vec![];
+            for idx in base + 2..=sys::lua_gettop(ptr) {
+                flags.push(String::try_from_lua(ptr, idx)?);
+            }
+            Ok(JitStatus { enabled, flags })
+        }
+    }
+
+    pub fn status(&self) -> JitStatus {
+        self.try_status().unwrap_display()
+    }
+}
@@ -3,11 +3,20 @@ pub mod helper;
 
 pub mod lua;
 
+pub mod cdata;
 pub mod func;
+pub mod hook;
+pub mod int64;
+pub mod jit;
 pub mod lstr;
 pub mod table;
+pub mod thread;
 pub mod ud;
 pub mod value;
+pub mod variadic;
+
+#[cfg(feature = "serde")]
+pub mod serde;
 
 pub mod from_lua;
 pub mod is_type;
@@ -20,6 +29,7 @@ pub use mlua_sys as sys;
 
 mod owned_value;
 mod stack_guard;
+mod weak;
 
 pub struct AnyUserData;
 
@@ -37,22 +47,55 @@ pub struct Nil;
 pub trait UserData {
     fn name() -> *const i8;
     fn functions() -> &'static [crate::sys::luaL_Reg];
+
+    fn meta_functions() -> &'static [crate::sys::luaL_Reg] {
+        empty_regs()
+    }
+
+    fn getters() -> &'static [crate::sys::luaL_Reg] {
+        empty_regs()
+    }
+
+    fn setters() -> &'static [crate::sys::luaL_Reg] {
+        empty_regs()
+    }
+
+    fn static_functions() -> &'static [crate::sys::luaL_Reg] {
+        empty_regs()
+    }
+}
+
+static EMPTY_REGS: [SyncLuaReg; 1] = [SyncLuaReg(sys::luaL_Reg {
+    name: std::ptr::null(),
+    func: dummy_trampoline,
+})];
+
+fn empty_regs() -> &'static [sys::luaL_Reg] {
+    unsafe { &*(&EMPTY_REGS as *const [SyncLuaReg; 1] as *const [sys::luaL_Reg; 1]) }
 }
 
 pub mod prelude {
     pub use crate::Nil;
     pub use crate::UserData;
+    pub use crate::cdata::{CDataRef, CType, StackCData};
     pub use crate::create_table;
     // pub use crate::error::{Error, UnwrapDisplay};
-    pub use crate::func::{FnRef, StackFn};
-    pub use crate::lstr::{StackStr, StrRef};
-    pub use crate::lua::Lua;
+    pub use crate::func::{FnRef, StackFn, WeakFnRef};
+    pub use crate::hook::{ExecutionLimit, HookEvent, HookTriggers};
+    pub use crate::int64::{BoxedI64, BoxedU64};
+    pub use crate::jit::{Jit, JitStatus};
+    pub use crate::lstr::{StackStr, StrRef, WeakStrRef};
+    pub use crate::lua::{Bundle, ChunkMode, Lua, LuaBuilder, Source};
     pub use crate::owned_value::OwnedValue;
     pub use crate::stack_guard::StackGuard;
-    pub use crate::table::{StackTable, TableRef, builder::TableBuilder, view::TableView};
-    pub use crate::ud::{StackUd, UdRef};
-    pub use crate::value::{StackValue, ValueRef};
-    pub use macros::{module, user_data};
+    pub use crate::table::{
+        StackTable, TableRef, WeakTableRef, builder::TableBuilder, view::TableView,
+    };
+    pub use crate::thread::{StackThread, ThreadRef, ThreadStatus};
+    pub use crate::ud::{StackUd, UdRef, WeakUdRef};
+    pub use crate::value::{StackValue, ValueRef, WeakValueRef};
+    pub use crate::variadic::{MultiValue, Variadic};
+    pub use macros::{FromLua, ToLua, include_lua, module, user_data};
 }
 
 pub trait Mode {}
@@ -63,6 +106,9 @@ impl Mode for Owned {}
 pub struct Borrowed;
 impl Mode for Borrowed {}
 
+pub struct Weak;
+impl Mode for Weak {}
+
 #[repr(transparent)]
 pub struct SyncLuaReg(pub sys::luaL_Reg);
 
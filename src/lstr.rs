@@ -5,14 +5,16 @@ use std::{
 };
 
 use crate::{
-    Borrowed, Mode, Owned,
+    Borrowed, Mode, Owned, Weak,
     error::{Error, UnwrapDisplay},
     from_lua::FromLua,
+    helper,
     is_type::IsType,
     lua::InnerLua,
     owned_value::{LuaInnerHandle, OwnedValue},
     sys,
     to_lua::ToLua,
+    weak::WeakSlot,
 };
 
 pub trait StringState {
@@ -76,8 +78,28 @@ impl StringAccess for OwnedState {
     }
 }
 
+#[derive(Debug)]
+pub struct WeakState {
+    slot: WeakSlot,
+    slice: &'static [u8],
+}
+
+impl StringState for Weak {
+    type State = WeakState;
+}
+
+impl StringAccess for WeakState {
+    fn try_as_slice<'a>(&'a self) -> Result<&'a [u8], Error> {
+        self.slot.try_with_target(|_| Ok(()))?;
+        Ok(self.slice)
+    }
+}
+
 pub type StackStr = LStr<Borrowed>;
 pub type StrRef = LStr<Owned>;
+/// Lua 5.1 never clears strings from weak tables, so a `WeakStrRef` keeps its
+/// string alive just like a `StrRef` does.
+pub type WeakStrRef = LStr<Weak>;
 
 pub struct LStr<M>
 where
@@ -155,6 +177,50 @@ impl StrRef {
         let state = OwnedState { id, lua, slice };
         Ok(Self { state })
     }
+
+    pub fn try_downgrade(&self) -> Result<WeakStrRef, Error> {
+        let lua = self.state.lua.borrow().clone();
+        let ptr = lua.try_state()?;
+        unsafe {
+            helper::try_check_stack(ptr, 1)?;
+            sys::lua_rawgeti(ptr, sys::LUA_REGISTRYINDEX, self.state.id as _);
+            let slot = WeakSlot::try_new(lua, ptr, -1);
+            sys::lua_pop(ptr, 1);
+
+            let slice = self.state.slice;
+            Ok(WeakStrRef {
+                state: WeakState { slot: slot?, slice },
+            })
+        }
+    }
+
+    pub fn downgrade(&self) -> WeakStrRef {
+        self.try_downgrade().unwrap_display()
+    }
+}
+
+impl WeakStrRef {
+    pub fn upgrade(&self) -> Option<StrRef> {
+        self.state
+            .slot
+            .try_with_target(|ptr| StrRef::try_from_lua(ptr, -1))
+            .ok()
+    }
+
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        let slot = self.state.slot.try_clone()?;
+        let slice = self.state.slice;
+        Ok(Self {
+            state: WeakState { slot, slice },
+        })
+    }
+}
+
+impl Clone for WeakStrRef {
+    #[inline]
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_display()
+    }
 }
 
 impl Clone for StrRef {
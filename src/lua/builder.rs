@@ -0,0 +1,35 @@
+use crate::{error::Error, error::UnwrapDisplay};
+
+use super::{InnerLua, Lua, memory};
+
+#[derive(Debug, Default)]
+pub struct LuaBuilder {
+    memory_limit: Option<usize>,
+}
+
+impl LuaBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    pub fn try_build(self) -> Result<Lua, Error> {
+        let state = Box::new(memory::MemoryState::new(self.memory_limit));
+        let ptr = unsafe { memory::new_state(&state) };
+        if ptr.is_null() {
+            Err(Error::StateAllocationFailed)
+        } else {
+            Ok(Lua {
+                inner: InnerLua::with_memory(ptr, Some(state)),
+            })
+        }
+    }
+
+    pub fn build(self) -> Lua {
+        self.try_build().unwrap_display()
+    }
+}
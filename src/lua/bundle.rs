@@ -0,0 +1,61 @@
+use crate::error::{Error, UnwrapDisplay};
+
+use super::{Lua, Source};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BundleFile {
+    module: &'static str,
+    path: &'static str,
+    code: &'static [u8],
+}
+
+impl BundleFile {
+    pub const fn new(module: &'static str, path: &'static str, code: &'static [u8]) -> Self {
+        Self { module, path, code }
+    }
+
+    pub fn module(&self) -> &'static str {
+        self.module
+    }
+
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+
+    pub fn code(&self) -> &'static [u8] {
+        self.code
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bundle {
+    files: &'static [BundleFile],
+}
+
+impl Bundle {
+    pub const fn new(files: &'static [BundleFile]) -> Self {
+        Self { files }
+    }
+
+    pub fn files(&self) -> &'static [BundleFile] {
+        self.files
+    }
+
+    pub fn get(&self, module: &str) -> Option<&'static BundleFile> {
+        self.files.iter().find(|f| f.module == module)
+    }
+}
+
+impl Lua {
+    pub fn try_install_bundle(&self, bundle: &'static Bundle) -> Result<(), Error> {
+        self.try_add_searcher(move |name| {
+            bundle
+                .get(name)
+                .map(|file| Source::new(file.code).name(file.path))
+        })
+    }
+
+    pub fn install_bundle(&self, bundle: &'static Bundle) {
+        self.try_install_bundle(bundle).unwrap_display()
+    }
+}
@@ -0,0 +1,135 @@
+use std::ffi::CString;
+
+use crate::{
+    error::Error,
+    from_lua::FromLua,
+    func::FnRef,
+    helper,
+    lua::{Lua, ValueArg},
+    stack_guard::StackGuard,
+    sys,
+    table::TableRef,
+    to_lua::ToLua,
+};
+
+const BYTECODE_SIGNATURE: u8 = 0x1b;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkMode {
+    Text,
+    Binary,
+    Both,
+}
+
+pub struct Chunk<'a> {
+    lua: &'a Lua,
+    code: &'a [u8],
+    name: Option<String>,
+    mode: ChunkMode,
+    allow_bytecode: bool,
+    env: Option<TableRef>,
+}
+
+impl<'a> Chunk<'a> {
+    pub(crate) fn new(lua: &'a Lua, code: &'a [u8]) -> Self {
+        Self {
+            lua,
+            code,
+            name: None,
+            mode: ChunkMode::Text,
+            allow_bytecode: false,
+            env: None,
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn mode(mut self, mode: ChunkMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub(crate) unsafe fn allow_bytecode(mut self) -> Self {
+        self.allow_bytecode = true;
+        self
+    }
+
+    pub fn env(mut self, env: TableRef) -> Self {
+        self.env = Some(env);
+        self
+    }
+
+    pub fn exec(self) -> Result<(), Error> {
+        self.eval::<()>()
+    }
+
+    pub fn eval<T: ValueArg + FromLua + ToLua>(self) -> Result<T, Error> {
+        self.lua.eval::<T, _>(|ptr| unsafe { self.load(ptr) })
+    }
+
+    pub fn into_function(self) -> Result<FnRef, Error> {
+        let ptr = self.lua.inner.try_state()?;
+        unsafe {
+            helper::try_check_stack(ptr, 2)?;
+            let _g = StackGuard::new(ptr);
+            if self.load(ptr)? != 0 {
+                let msg = <String as FromLua>::try_from_lua(ptr, -1)?;
+                return Err(Error::InvalidSyntax(msg));
+            }
+            FnRef::try_from_lua(ptr, -1)
+        }
+    }
+
+    fn chunk_name(&self) -> Result<CString, Error> {
+        match &self.name {
+            Some(name) if name.starts_with('@') || name.starts_with('=') => {
+                Ok(CString::new(name.as_str())?)
+            }
+            Some(name) => Ok(CString::new(format!("@{name}"))?),
+            None => Ok(CString::new(self.code).unwrap_or_else(|_| c"=(load)".into())),
+        }
+    }
+
+    unsafe fn load(&self, ptr: *mut sys::lua_State) -> Result<std::ffi::c_int, Error> {
+        let is_binary = self.code.first() == Some(&BYTECODE_SIGNATURE);
+        match (self.mode, is_binary) {
+            (ChunkMode::Text, true) => {
+                return Err(Error::InvalidSyntax(
+                    "attempt to load a binary chunk (mode is 'text')".into(),
+                ));
+            }
+            (ChunkMode::Binary, false) => {
+                return Err(Error::InvalidSyntax(
+                    "attempt to load a text chunk (mode is 'binary')".into(),
+                ));
+            }
+            (_, true) if !self.allow_bytecode => {
+                return Err(Error::InvalidSyntax(
+                    "binary chunks can only be loaded through Lua::load_bytecode".into(),
+                ));
+            }
+            _ => {}
+        }
+
+        let name = self.chunk_name()?;
+        unsafe {
+            helper::try_check_stack(ptr, 2)?;
+            let status = sys::luaL_loadbuffer(
+                ptr,
+                self.code.as_ptr() as _,
+                self.code.len(),
+                name.as_ptr(),
+            );
+            if status == 0
+                && let Some(env) = &self.env
+            {
+                env.try_to_lua_unchecked(ptr)?;
+                sys::lua_setfenv(ptr, -2);
+            }
+            Ok(status)
+        }
+    }
+}
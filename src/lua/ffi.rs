@@ -0,0 +1,76 @@
+use std::ffi::CString;
+
+use crate::{
+    cdata::{self, CDataRef, CType},
+    error::{Error, UnwrapDisplay},
+    from_lua::FromLua,
+    helper,
+    stack_guard::StackGuard,
+    sys,
+};
+
+use super::Lua;
+
+impl Lua {
+    pub fn try_cdef(&self, decl: &str) -> Result<(), Error> {
+        let ptr = self.inner.try_state()?;
+        let decl = CString::new(decl)?;
+        unsafe {
+            helper::try_check_stack(ptr, 2)?;
+            let _g = StackGuard::new(ptr);
+            cdata::push_ffi_fn(ptr, c"cdef")?;
+            sys::lua_pushstring(ptr, decl.as_ptr());
+            helper::pcall(ptr, 1, 0)
+        }
+    }
+
+    pub fn cdef(&self, decl: &str) {
+        self.try_cdef(decl).unwrap_display()
+    }
+
+    pub fn try_register_ctype<T: CType>(&self) -> Result<(), Error> {
+        let ptr = self.inner.try_state()?;
+        let name = CString::new(T::NAME)?;
+        if !unsafe { cdata::is_declared(ptr, &name)? } {
+            self.try_cdef(T::CDEF)?;
+        }
+        Ok(())
+    }
+
+    pub fn register_ctype<T: CType>(&self) {
+        self.try_register_ctype::<T>().unwrap_display()
+    }
+
+    pub fn try_create_cdata<T: CType>(&self, value: T) -> Result<CDataRef, Error> {
+        self.try_register_ctype::<T>()?;
+
+        let ptr = self.inner.try_state()?;
+        let name = CString::new(T::NAME)?;
+        unsafe {
+            helper::try_check_stack(ptr, 2)?;
+            let _g = StackGuard::new(ptr);
+
+            cdata::push_ffi_fn(ptr, c"sizeof")?;
+            sys::lua_pushstring(ptr, name.as_ptr());
+            helper::pcall(ptr, 1, 1)?;
+            let size = sys::lua_tointeger(ptr, -1) as usize;
+            if size != std::mem::size_of::<T>() {
+                return Err(Error::CTypeSizeMismatch(
+                    T::NAME.to_string(),
+                    size,
+                    std::mem::size_of::<T>(),
+                ));
+            }
+
+            cdata::push_ffi_fn(ptr, c"new")?;
+            sys::lua_pushstring(ptr, name.as_ptr());
+            helper::pcall(ptr, 1, 1)?;
+            std::ptr::write(sys::lua_topointer(ptr, -1) as *mut T, value);
+            CDataRef::try_from_lua(ptr, -1)
+        }
+    }
+
+    pub fn create_cdata<T: CType>(&self, value: T) -> CDataRef {
+        self.try_create_cdata(value).unwrap_display()
+    }
+}
@@ -0,0 +1,94 @@
+use std::ffi::c_int;
+
+use crate::{
+    error::{Error, UnwrapDisplay},
+    helper,
+    stack_guard::StackGuard,
+    sys,
+};
+
+use super::Lua;
+
+unsafe extern "C-unwind" fn gc_trampoline(ptr: *mut sys::lua_State) -> c_int {
+    unsafe {
+        let what = sys::lua_tointeger(ptr, 1) as c_int;
+        let data = sys::lua_tointeger(ptr, 2) as c_int;
+        let result = sys::lua_gc(ptr, what, data);
+        sys::lua_pushinteger(ptr, result as _);
+    }
+    1
+}
+
+impl Lua {
+    fn try_gc(&self, what: c_int, data: c_int) -> Result<c_int, Error> {
+        let ptr = self.inner.try_state()?;
+        unsafe {
+            helper::try_check_stack(ptr, 3)?;
+            let _g = StackGuard::new(ptr);
+            sys::lua_pushcfunction(ptr, gc_trampoline);
+            sys::lua_pushinteger(ptr, what as _);
+            sys::lua_pushinteger(ptr, data as _);
+            helper::pcall(ptr, 2, 1)?;
+            Ok(sys::lua_tointeger(ptr, -1) as c_int)
+        }
+    }
+
+    pub fn try_gc_collect(&self) -> Result<(), Error> {
+        self.try_gc(sys::LUA_GCCOLLECT, 0).map(|_| ())
+    }
+
+    pub fn gc_collect(&self) {
+        self.try_gc_collect().unwrap_display()
+    }
+
+    pub fn try_gc_step(&self, kb: i32) -> Result<bool, Error> {
+        self.try_gc(sys::LUA_GCSTEP, kb)
+            .map(|finished| finished != 0)
+    }
+
+    pub fn gc_step(&self, kb: i32) -> bool {
+        self.try_gc_step(kb).unwrap_display()
+    }
+
+    pub fn try_gc_stop(&self) -> Result<(), Error> {
+        self.try_gc(sys::LUA_GCSTOP, 0).map(|_| ())
+    }
+
+    pub fn gc_stop(&self) {
+        self.try_gc_stop().unwrap_display()
+    }
+
+    pub fn try_gc_restart(&self) -> Result<(), Error> {
+        self.try_gc(sys::LUA_GCRESTART, 0).map(|_| ())
+    }
+
+    pub fn gc_restart(&self) {
+        self.try_gc_restart().unwrap_display()
+    }
+
+    pub fn try_gc_count_bytes(&self) -> Result<usize, Error> {
+        let kb = self.try_gc(sys::LUA_GCCOUNT, 0)? as usize;
+        let bytes = self.try_gc(sys::LUA_GCCOUNTB, 0)? as usize;
+        Ok(kb * 1024 + bytes)
+    }
+
+    pub fn gc_count_bytes(&self) -> usize {
+        self.try_gc_count_bytes().unwrap_display()
+    }
+
+    pub fn try_gc_set_pause(&self, pause: i32) -> Result<i32, Error> {
+        self.try_gc(sys::LUA_GCSETPAUSE, pause)
+    }
+
+    pub fn gc_set_pause(&self, pause: i32) -> i32 {
+        self.try_gc_set_pause(pause).unwrap_display()
+    }
+
+    pub fn try_gc_set_step_multiplier(&self, multiplier: i32) -> Result<i32, Error> {
+        self.try_gc(sys::LUA_GCSETSTEPMUL, multiplier)
+    }
+
+    pub fn gc_set_step_multiplier(&self, multiplier: i32) -> i32 {
+        self.try_gc_set_step_multiplier(multiplier).unwrap_display()
+    }
+}
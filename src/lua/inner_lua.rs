@@ -3,6 +3,8 @@ use std::{cell::Cell, ptr, rc::Rc};
 
 use crate::error::Error;
 
+use super::memory::MemoryState;
+
 static CTX_KEY: u8 = 0;
 
 unsafe extern "C-unwind" fn individual_sentinel_gc(ptr: *mut sys::lua_State) -> i32 {
@@ -26,6 +28,7 @@ pub struct InnerLua {
     thread_ref: Option<i32>,
     cache_key: *mut std::ffi::c_void,
     vm_id: *const std::ffi::c_void,
+    _memory: Option<Box<MemoryState>>,
 }
 
 unsafe fn get_vm_id(ptr: *mut sys::lua_State) -> *const std::ffi::c_void {
@@ -34,6 +37,13 @@ unsafe fn get_vm_id(ptr: *mut sys::lua_State) -> *const std::ffi::c_void {
 
 impl InnerLua {
     pub(crate) fn new(ptr: *mut sys::lua_State) -> Rc<Self> {
+        Self::with_memory(ptr, None)
+    }
+
+    pub(crate) fn with_memory(
+        ptr: *mut sys::lua_State,
+        memory: Option<Box<MemoryState>>,
+    ) -> Rc<Self> {
         let cache_key = &CTX_KEY as *const u8 as *mut std::ffi::c_void;
         let inner = Rc::new(InnerLua {
             state: Cell::new(ptr),
@@ -41,6 +51,7 @@ impl InnerLua {
             thread_ref: None,
             cache_key,
             vm_id: unsafe { get_vm_id(ptr) },
+            _memory: memory,
         });
         unsafe { InnerLua::create_and_cache_sentinel(ptr, cache_key, inner.clone()) };
         inner
@@ -154,6 +165,7 @@ impl InnerLua {
                 thread_ref,
                 cache_key,
                 vm_id: get_vm_id(ptr),
+                _memory: None,
             });
 
             Self::create_and_cache_sentinel(ptr, cache_key, inner.clone());
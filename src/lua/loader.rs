@@ -0,0 +1,145 @@
+use std::ffi::CString;
+
+use crate::{
+    error::{Error, UnwrapDisplay},
+    func::FnRef,
+    helper,
+    stack_guard::StackGuard,
+    sys,
+    to_lua::ToLua,
+};
+
+use super::Lua;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    code: Vec<u8>,
+    name: Option<String>,
+}
+
+impl Source {
+    pub fn new<C: Into<Vec<u8>>>(code: C) -> Self {
+        Self {
+            code: code.into(),
+            name: None,
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+}
+
+impl From<&str> for Source {
+    fn from(value: &str) -> Self {
+        Source::new(value)
+    }
+}
+
+impl From<String> for Source {
+    fn from(value: String) -> Self {
+        Source::new(value)
+    }
+}
+
+impl From<&[u8]> for Source {
+    fn from(value: &[u8]) -> Self {
+        Source::new(value)
+    }
+}
+
+impl From<Vec<u8>> for Source {
+    fn from(value: Vec<u8>) -> Self {
+        Source::new(value)
+    }
+}
+
+unsafe fn push_package_field(
+    ptr: *mut sys::lua_State,
+    field: &std::ffi::CStr,
+) -> Result<(), Error> {
+    unsafe {
+        helper::try_check_stack(ptr, 2)?;
+        helper::push_lib(ptr, c"package", sys::luaopen_package)?;
+        sys::lua_getfield(ptr, -1, field.as_ptr());
+        sys::lua_remove(ptr, -2);
+        if sys::lua_istable(ptr, -1) == 0 {
+            sys::lua_pop(ptr, 1);
+            return Err(Error::MissingGlobal(format!(
+                "package.{}",
+                field.to_string_lossy()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Lua {
+    pub fn try_preload<F, R>(&self, name: &str, f: F) -> Result<(), Error>
+    where
+        F: Fn(&Lua) -> Result<R, Error> + 'static,
+        R: ToLua,
+    {
+        let ptr = self.inner.try_state()?;
+        let cname = CString::new(name)?;
+        let loader = self.try_create_function(move |lua, _: String| f(lua))?;
+
+        unsafe {
+            helper::try_check_stack(ptr, 2)?;
+            let _g = StackGuard::new(ptr);
+            push_package_field(ptr, c"preload")?;
+            loader.try_to_lua_unchecked(ptr)?;
+            sys::lua_setfield(ptr, -2, cname.as_ptr());
+        }
+        Ok(())
+    }
+
+    pub fn preload<F, R>(&self, name: &str, f: F)
+    where
+        F: Fn(&Lua) -> Result<R, Error> + 'static,
+        R: ToLua,
+    {
+        self.try_preload(name, f).unwrap_display()
+    }
+
+    pub fn try_add_searcher<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: Fn(&str) -> Option<Source> + 'static,
+    {
+        let ptr = self.inner.try_state()?;
+        let searcher = self.try_create_function(move |lua, name: String| {
+            let Some(source) = f(&name) else {
+                return Ok(None::<FnRef>);
+            };
+            let chunk_name = source.name.as_deref().unwrap_or(&name);
+            lua.load(&source.code)
+                .name(chunk_name)
+                .into_function()
+                .map(Some)
+        })?;
+
+        unsafe {
+            helper::try_check_stack(ptr, 3)?;
+            let _g = StackGuard::new(ptr);
+            push_package_field(ptr, c"loaders")?;
+
+            let len = sys::lua_objlen(ptr, -1) as i32;
+            let pos = len.min(1) + 1;
+            for i in (pos..=len).rev() {
+                sys::lua_rawgeti(ptr, -1, i as _);
+                sys::lua_rawseti(ptr, -2, (i + 1) as _);
+            }
+            searcher.try_to_lua_unchecked(ptr)?;
+            sys::lua_rawseti(ptr, -2, pos as _);
+        }
+        Ok(())
+    }
+
+    pub fn add_searcher<F>(&self, f: F)
+    where
+        F: Fn(&str) -> Option<Source> + 'static,
+    {
+        self.try_add_searcher(f).unwrap_display()
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    alloc::{self, Layout},
+    cell::Cell,
+    ffi::c_void,
+};
+
+use crate::sys;
+
+const ALLOC_ALIGN: usize = 16;
+
+#[derive(Debug, Default)]
+pub(crate) struct MemoryState {
+    used: Cell<usize>,
+    peak: Cell<usize>,
+    limit: Option<usize>,
+    enforced: Cell<bool>,
+}
+
+impl MemoryState {
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    pub(crate) fn peak(&self) -> usize {
+        self.peak.get()
+    }
+}
+
+unsafe extern "C" fn allocator(
+    ud: *mut c_void,
+    ptr: *mut c_void,
+    osize: usize,
+    nsize: usize,
+) -> *mut c_void {
+    unsafe {
+        let state = &*(ud as *const MemoryState);
+        let osize = if ptr.is_null() { 0 } else { osize };
+
+        if nsize == 0 {
+            if !ptr.is_null() {
+                alloc::dealloc(
+                    ptr as _,
+                    Layout::from_size_align_unchecked(osize, ALLOC_ALIGN),
+                );
+                state.used.set(state.used.get() - osize);
+            }
+            return std::ptr::null_mut();
+        }
+
+        let used = state.used.get() + nsize - osize;
+        if nsize > osize
+            && state.enforced.get()
+            && let Some(limit) = state.limit
+            && used > limit
+        {
+            return std::ptr::null_mut();
+        }
+
+        let Ok(layout) = Layout::from_size_align(nsize, ALLOC_ALIGN) else {
+            return std::ptr::null_mut();
+        };
+        let new_ptr = if ptr.is_null() {
+            alloc::alloc(layout)
+        } else {
+            let old = Layout::from_size_align_unchecked(osize, ALLOC_ALIGN);
+            alloc::realloc(ptr as _, old, nsize)
+        };
+        if new_ptr.is_null() {
+            return std::ptr::null_mut();
+        }
+
+        state.used.set(used);
+        state.peak.set(state.peak.get().max(used));
+        new_ptr as _
+    }
+}
+
+pub(crate) unsafe fn new_state(state: &MemoryState) -> *mut sys::lua_State {
+    unsafe { sys::lua_newstate(allocator, state as *const MemoryState as *mut c_void) }
+}
+
+pub(crate) unsafe fn memory_state<'a>(ptr: *mut sys::lua_State) -> Option<&'a MemoryState> {
+    unsafe {
+        let mut ud = std::ptr::null_mut();
+        let f = sys::lua_getallocf(ptr, &mut ud);
+        if f as usize == allocator as sys::lua_Alloc as usize && !ud.is_null() {
+            Some(&*(ud as *const MemoryState))
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) unsafe fn limit_exceeded(ptr: *mut sys::lua_State, status: std::ffi::c_int) -> bool {
+    status == sys::LUA_ERRMEM && unsafe { memory_state(ptr) }.is_some_and(|m| m.limit.is_some())
+}
+
+pub(crate) unsafe fn set_enforced(ptr: *mut sys::lua_State, enforced: bool) -> bool {
+    match unsafe { memory_state(ptr) } {
+        Some(state) => state.enforced.replace(enforced),
+        None => false,
+    }
+}
@@ -1,20 +1,39 @@
+mod builder;
+mod bundle;
+mod chunk;
+mod ffi;
+mod gc;
 mod inner_lua;
+mod loader;
+pub(crate) mod memory;
+mod sandbox;
+pub use builder::LuaBuilder;
+pub use bundle::{Bundle, BundleFile};
+pub use chunk::{Chunk, ChunkMode};
 pub(crate) use inner_lua::InnerLua;
+pub use loader::Source;
+pub use sandbox::SAFE_GLOBALS;
 
 use macros::generate_value_arg_tuple_impl;
 
 use crate::{
     Borrowed,
+    cdata::CDataRef,
     error::UnwrapDisplay,
     func::FnRef,
     helper,
+    hook::{self, ExecutionLimit, HookEvent, HookTriggers},
+    int64::{BoxedI64, BoxedU64},
+    jit::{self, Jit},
     lstr::StrRef,
     prelude::TableView,
     stack_guard::StackGuard,
     sys,
     table::{StackTable, TableRef},
+    thread::ThreadRef,
     ud::UdRef,
     value::ValueRef,
+    variadic::Variadic,
 };
 use std::{ffi::CString, fmt::Display, rc::Rc};
 
@@ -44,6 +63,10 @@ impl Lua {
         Self::try_new().unwrap_display()
     }
 
+    pub fn builder() -> LuaBuilder {
+        LuaBuilder::new()
+    }
+
     pub unsafe fn assert_main_state(&self) -> Result<(), Error> {
         let ptr = self.inner.try_state()?;
         let is_main = unsafe { sys::lua_pushthread(ptr) == 1 };
@@ -115,7 +138,14 @@ impl Lua {
     }
 
     pub fn try_open_libs(&self) -> Result<(), Error> {
-        unsafe { sys::luaL_openlibs(self.inner.try_state()?) };
+        let ptr = self.inner.try_state()?;
+        unsafe {
+            sys::luaL_openlibs(ptr);
+            jit::record_enabled(ptr, true)?;
+            if hook::suspends_jit(ptr) {
+                jit::set_enabled(ptr, false);
+            }
+        }
         Ok(())
     }
 
@@ -140,6 +170,65 @@ impl Lua {
         Table::new(self.inner.clone())
     }
 
+    pub fn jit(&self) -> Jit {
+        Jit::new(self.inner.clone())
+    }
+
+    pub fn try_set_hook<F>(&self, triggers: HookTriggers, callback: F) -> Result<(), Error>
+    where
+        F: Fn(&Lua, HookEvent) -> Result<(), Error> + 'static,
+    {
+        let ptr = self.inner.try_state()?;
+        unsafe { hook::set_hook(ptr, triggers, Box::new(callback), None) }
+    }
+
+    pub fn set_hook<F>(&self, triggers: HookTriggers, callback: F)
+    where
+        F: Fn(&Lua, HookEvent) -> Result<(), Error> + 'static,
+    {
+        self.try_set_hook(triggers, callback).unwrap_display()
+    }
+
+    pub fn try_remove_hook(&self) -> Result<(), Error> {
+        let ptr = self.inner.try_state()?;
+        unsafe { hook::remove_hook(ptr) }
+    }
+
+    pub fn remove_hook(&self) {
+        self.try_remove_hook().unwrap_display()
+    }
+
+    pub fn try_set_execution_limit<L: Into<ExecutionLimit>>(&self, limit: L) -> Result<(), Error> {
+        let ptr = self.inner.try_state()?;
+        let (triggers, callback, reset) = hook::execution_limit(limit.into());
+        unsafe { hook::set_hook(ptr, triggers, callback, Some(reset)) }
+    }
+
+    pub fn set_execution_limit<L: Into<ExecutionLimit>>(&self, limit: L) {
+        self.try_set_execution_limit(limit).unwrap_display()
+    }
+
+    pub fn try_create_sandbox(&self) -> Result<TableRef, Error> {
+        self.try_create_sandbox_with(SAFE_GLOBALS)
+    }
+
+    pub fn create_sandbox(&self) -> TableRef {
+        self.try_create_sandbox().unwrap_display()
+    }
+
+    pub fn try_create_sandbox_with(&self, allowlist: &[&str]) -> Result<TableRef, Error> {
+        let ptr = self.inner.try_state()?;
+        unsafe {
+            let _g = StackGuard::new(ptr);
+            sandbox::push_sandbox(ptr, allowlist)?;
+            TableRef::try_from_lua(ptr, -1)
+        }
+    }
+
+    pub fn create_sandbox_with(&self, allowlist: &[&str]) -> TableRef {
+        self.try_create_sandbox_with(allowlist).unwrap_display()
+    }
+
     pub fn try_create_value_ref<T: ToLua>(&self, value: T) -> Result<ValueRef, Error> {
         let ptr = self.inner.try_state()?;
         <T as ToLua>::try_to_lua(value, ptr)?;
@@ -172,6 +261,32 @@ impl Lua {
         StrRef::new(self.inner.clone(), value)
     }
 
+    pub fn try_create_function<F, A, R>(&self, f: F) -> Result<FnRef, Error>
+    where
+        F: Fn(&Lua, A) -> Result<R, Error> + 'static,
+        A: FromLua + ValueArg,
+        R: ToLua,
+    {
+        FnRef::try_new(self.inner.clone(), f)
+    }
+
+    pub fn create_function<F, A, R>(&self, f: F) -> FnRef
+    where
+        F: Fn(&Lua, A) -> Result<R, Error> + 'static,
+        A: FromLua + ValueArg,
+        R: ToLua,
+    {
+        self.try_create_function(f).unwrap_display()
+    }
+
+    pub fn try_create_thread(&self, func: &FnRef) -> Result<ThreadRef, Error> {
+        ThreadRef::try_new(self.inner.clone(), func)
+    }
+
+    pub fn create_thread(&self, func: &FnRef) -> ThreadRef {
+        self.try_create_thread(func).unwrap_display()
+    }
+
     pub fn try_register<T: ToLua>(&self, lib_name: &str, lib_instance: T) -> Result<(), Error> {
         let ptr = self.inner.try_state()?;
         let cname = std::ffi::CString::new(lib_name)?;
@@ -195,6 +310,41 @@ impl Lua {
         self.try_register(lib_name, lib_instance).unwrap_display()
     }
 
+    pub fn try_register_type<T: UserData>(&self, name: &str) -> Result<(), Error> {
+        let ptr = self.inner.try_state()?;
+        let cname = std::ffi::CString::new(name)?;
+
+        unsafe {
+            helper::try_check_stack(ptr, 2)?;
+
+            sys::lua_newtable(ptr);
+            sys::luaL_register(ptr, std::ptr::null(), T::static_functions().as_ptr());
+            sys::lua_setfield(ptr, sys::LUA_GLOBALSINDEX, cname.as_ptr());
+        }
+
+        Ok(())
+    }
+
+    pub fn register_type<T: UserData>(&self, name: &str) {
+        self.try_register_type::<T>(name).unwrap_display()
+    }
+
+    pub fn load<'a, C: AsRef<[u8]> + ?Sized>(&'a self, code: &'a C) -> Chunk<'a> {
+        Chunk::new(self, code.as_ref())
+    }
+
+    /// # Safety
+    ///
+    /// LuaJIT does not verify bytecode, so `bytecode` must come from a trusted
+    /// `dump` made by the same LuaJIT build.
+    pub unsafe fn load_bytecode<'a>(&'a self, bytecode: &'a [u8]) -> Chunk<'a> {
+        unsafe {
+            Chunk::new(self, bytecode)
+                .mode(ChunkMode::Binary)
+                .allow_bytecode()
+        }
+    }
+
     pub fn exec(&mut self, code: &str) -> Result<(), Error> {
         self.do_string::<()>(code)
     }
@@ -237,52 +387,69 @@ impl Lua {
         X: FnOnce(&T) -> R,
         R,
     >(
-        &mut self,
+        &self,
         f: F,
         x: X,
     ) -> Result<R, Error> {
         let ptr = self.inner.try_state()?;
         let _g = StackGuard::new(ptr);
+        let base = unsafe { sys::lua_gettop(ptr) };
 
         if f(ptr)? != 0 {
             let msg = <String as FromLua>::try_from_lua(ptr, -1).unwrap_or_default();
             return Err(Error::InvalidSyntax(msg));
         }
 
-        if unsafe { sys::lua_pcall(ptr, 0, <T as FromLua>::len(), 0) } != 0 {
-            unsafe { Err(Error::from_stack(ptr, -1)) }
-        } else {
-            let size = <T as FromLua>::len();
-            let value = T::try_from_lua(ptr, -size).map_err(|_| Error::WrongReturnType)?;
-            let result = x(&value);
-            Ok(result)
-        }
+        unsafe { helper::pcall(ptr, 0, helper::nresults::<T>())? };
+        let value = T::try_from_lua(ptr, base + 1).map_err(|_| Error::WrongReturnType)?;
+        let result = x(&value);
+        Ok(result)
     }
 
     fn eval<
         T: ValueArg + FromLua + ToLua,
         F: FnOnce(*mut sys::lua_State) -> Result<std::ffi::c_int, Error>,
     >(
-        &mut self,
+        &self,
         f: F,
     ) -> Result<T, Error> {
         let ptr = self.inner.try_state()?;
         let _g = StackGuard::new(ptr);
+        let base = unsafe { sys::lua_gettop(ptr) };
 
         if f(ptr)? != 0 {
             let msg = <String as FromLua>::try_from_lua(ptr, -1)?;
             return Err(Error::InvalidSyntax(msg));
         }
 
-        if unsafe { sys::lua_pcall(ptr, 0, <T as FromLua>::len(), 0) } != 0 {
-            unsafe { Err(Error::from_stack(ptr, -1)) }
-        } else {
-            let size = <T as FromLua>::len();
-            let value = T::try_from_lua(ptr, -size).map_err(|_| Error::WrongReturnType)?;
-            Ok(value)
+        unsafe { helper::pcall(ptr, 0, helper::nresults::<T>())? };
+        let value = T::try_from_lua(ptr, base + 1).map_err(|_| Error::WrongReturnType)?;
+        Ok(value)
+    }
+
+    pub fn try_used_memory(&self) -> Result<usize, Error> {
+        let ptr = self.inner.try_state()?;
+        unsafe {
+            match memory::memory_state(ptr) {
+                Some(state) => Ok(state.used()),
+                None => self.try_gc_count_bytes(),
+            }
         }
     }
 
+    pub fn used_memory(&self) -> usize {
+        self.try_used_memory().unwrap_display()
+    }
+
+    pub fn try_peak_memory(&self) -> Result<Option<usize>, Error> {
+        let ptr = self.inner.try_state()?;
+        Ok(unsafe { memory::memory_state(ptr) }.map(|state| state.peak()))
+    }
+
+    pub fn peak_memory(&self) -> Option<usize> {
+        self.try_peak_memory().unwrap_display()
+    }
+
     pub fn try_top(&self) -> Result<i32, Error> {
         Ok(unsafe { sys::lua_gettop(self.inner.try_state()?) })
     }
@@ -360,7 +527,16 @@ macro_rules! impl_value_arg {
 impl_value_arg!(
     (),
     Nil,
+    i8,
+    i16,
     i32,
+    i64,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    usize,
     f32,
     f64,
     bool,
@@ -368,6 +544,11 @@ impl_value_arg!(
     StrRef,
     TableRef,
     FnRef,
+    ThreadRef,
+    ValueRef,
+    CDataRef,
+    BoxedI64,
+    BoxedU64,
     Vec<u8>
 );
 
@@ -375,6 +556,8 @@ unsafe impl<T> ValueArg for UdRef<T> where T: UserData {}
 
 unsafe impl<T> ValueArg for Option<T> where T: FromLua + ValueArg {}
 
+unsafe impl<T> ValueArg for Variadic<T> where T: FromLua + ValueArg {}
+
 generate_value_arg_tuple_impl!();
 
 pub fn ensure_value_arg<T: ValueArg>() {}
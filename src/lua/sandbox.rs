@@ -0,0 +1,131 @@
+use std::ffi::CString;
+
+use crate::{error::Error, helper, sys};
+
+pub const SAFE_GLOBALS: &[&str] = &[
+    "assert",
+    "error",
+    "ipairs",
+    "next",
+    "pairs",
+    "pcall",
+    "print",
+    "rawequal",
+    "rawget",
+    "rawset",
+    "select",
+    "setmetatable",
+    "getmetatable",
+    "tonumber",
+    "tostring",
+    "type",
+    "unpack",
+    "xpcall",
+    "_VERSION",
+    "coroutine",
+    "math",
+    "string",
+    "table",
+    "bit",
+    "os.clock",
+    "os.date",
+    "os.difftime",
+    "os.time",
+];
+
+unsafe extern "C-unwind" fn table_getmetatable(ptr: *mut sys::lua_State) -> std::ffi::c_int {
+    unsafe {
+        if sys::lua_type(ptr, 1) != sys::LUA_TTABLE || sys::lua_getmetatable(ptr, 1) == 0 {
+            sys::lua_pushnil(ptr);
+            return 1;
+        }
+        sys::lua_pushstring(ptr, c"__metatable".as_ptr());
+        sys::lua_rawget(ptr, -2);
+        if sys::lua_isnil(ptr, -1) != 0 {
+            sys::lua_pop(ptr, 1);
+        }
+        1
+    }
+}
+
+unsafe fn push_copy(ptr: *mut sys::lua_State, idx: i32) {
+    unsafe {
+        let idx = sys::lua_absindex(ptr, idx);
+        if sys::lua_istable(ptr, idx) == 0 {
+            sys::lua_pushvalue(ptr, idx);
+            return;
+        }
+
+        sys::lua_newtable(ptr);
+        sys::lua_pushnil(ptr);
+        while sys::lua_next(ptr, idx) != 0 {
+            sys::lua_pushvalue(ptr, -2);
+            sys::lua_insert(ptr, -2);
+            sys::lua_rawset(ptr, -4);
+        }
+    }
+}
+
+unsafe fn push_subtable(ptr: *mut sys::lua_State, idx: i32, name: &CString) {
+    unsafe {
+        sys::lua_getfield(ptr, idx, name.as_ptr());
+        if sys::lua_istable(ptr, -1) == 0 {
+            sys::lua_pop(ptr, 1);
+            sys::lua_newtable(ptr);
+            sys::lua_pushvalue(ptr, -1);
+            sys::lua_setfield(ptr, idx, name.as_ptr());
+        }
+    }
+}
+
+pub(crate) unsafe fn push_sandbox(
+    ptr: *mut sys::lua_State,
+    allowlist: &[&str],
+) -> Result<(), Error> {
+    unsafe {
+        helper::try_check_stack(ptr, 6)?;
+        sys::lua_newtable(ptr);
+        let env = sys::lua_gettop(ptr);
+
+        for entry in allowlist {
+            match entry.split_once('.') {
+                None if *entry == "getmetatable" => {
+                    sys::lua_pushcfunction(ptr, table_getmetatable);
+                    sys::lua_setfield(ptr, env, c"getmetatable".as_ptr());
+                }
+                None => {
+                    let name = CString::new(*entry)?;
+                    sys::lua_getfield(ptr, sys::LUA_GLOBALSINDEX, name.as_ptr());
+                    if sys::lua_isnil(ptr, -1) == 0 {
+                        push_copy(ptr, -1);
+                        if *entry == "string" {
+                            sys::lua_pushnil(ptr);
+                            sys::lua_setfield(ptr, -2, c"dump".as_ptr());
+                        }
+                        sys::lua_setfield(ptr, env, name.as_ptr());
+                    }
+                    sys::lua_pop(ptr, 1);
+                }
+                Some((lib, field)) => {
+                    let lib = CString::new(lib)?;
+                    let field = CString::new(field)?;
+                    sys::lua_getfield(ptr, sys::LUA_GLOBALSINDEX, lib.as_ptr());
+                    if sys::lua_istable(ptr, -1) != 0 {
+                        sys::lua_getfield(ptr, -1, field.as_ptr());
+                        if sys::lua_isnil(ptr, -1) == 0 {
+                            push_subtable(ptr, env, &lib);
+                            sys::lua_insert(ptr, -2);
+                            sys::lua_setfield(ptr, -2, field.as_ptr());
+                        }
+                        sys::lua_pop(ptr, 1);
+                    }
+                    sys::lua_pop(ptr, 1);
+                }
+            }
+        }
+
+        sys::lua_pushvalue(ptr, env);
+        sys::lua_setfield(ptr, env, c"_G".as_ptr());
+    }
+    Ok(())
+}
@@ -0,0 +1,447 @@
+use ::serde::de::{self, IntoDeserializer, Visitor};
+
+use crate::{error::Error, from_lua::integer_from_number, helper, stack_guard::StackGuard, sys};
+
+use super::{Options, SparseArrays, is_null};
+
+const MAX_SPARSE_RATIO: usize = 2;
+
+pub(crate) struct Deserializer {
+    ptr: *mut sys::lua_State,
+    idx: i32,
+    options: Options,
+}
+
+impl Deserializer {
+    pub(crate) fn new(ptr: *mut sys::lua_State, idx: i32, options: Options) -> Self {
+        Self { ptr, idx, options }
+    }
+
+    fn lua_type(&self) -> i32 {
+        unsafe { sys::lua_type(self.ptr, self.idx) }
+    }
+
+    fn is_none(&self) -> bool {
+        self.lua_type() == sys::LUA_TNIL || unsafe { is_null(self.ptr, self.idx) }
+    }
+
+    fn to_bytes(&self) -> Option<&[u8]> {
+        if self.lua_type() != sys::LUA_TSTRING {
+            return None;
+        }
+        unsafe {
+            let mut len = 0;
+            let data = sys::lua_tolstring(self.ptr, self.idx, &mut len);
+            Some(std::slice::from_raw_parts(data as *const u8, len))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        let name = unsafe {
+            std::ffi::CStr::from_ptr(sys::lua_typename(self.ptr, self.lua_type()))
+                .to_string_lossy()
+                .to_string()
+        };
+        Error::DeserializeError(format!("invalid type: lua {}, expected {}", name, expected))
+    }
+
+    fn array_len(&self) -> Result<Option<(usize, usize)>, Error> {
+        unsafe {
+            helper::try_check_stack(self.ptr, 2)?;
+            let mut count = 0;
+            let mut max = 0;
+            sys::lua_pushnil(self.ptr);
+            while sys::lua_next(self.ptr, self.idx) != 0 {
+                let key = match sys::lua_type(self.ptr, -2) {
+                    sys::LUA_TNUMBER => {
+                        integer_from_number::<usize>(sys::lua_tonumber(self.ptr, -2))
+                    }
+                    _ => None,
+                };
+                sys::lua_pop(self.ptr, 1);
+                match key {
+                    Some(key) if key > 0 => {
+                        count += 1;
+                        max = max.max(key);
+                    }
+                    _ => {
+                        sys::lua_pop(self.ptr, 1);
+                        return Ok(None);
+                    }
+                }
+            }
+            Ok(Some((count, max)))
+        }
+    }
+
+    fn seq_len(&self, count: usize, max: usize) -> Result<usize, Error> {
+        let fill = self.options.sparse_arrays == SparseArrays::Fill
+            && max <= count.saturating_mul(MAX_SPARSE_RATIO);
+        if count == max || fill {
+            Ok(max)
+        } else {
+            Err(Error::DeserializeError(format!(
+                "sparse array with {} of {} elements",
+                count, max
+            )))
+        }
+    }
+
+    fn visit_seq<'de, V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        let mut seq = SeqDeserializer {
+            de: &self,
+            index: 0,
+            len,
+        };
+        let value = visitor.visit_seq(&mut seq)?;
+        if seq.index < seq.len {
+            return Err(de::Error::invalid_length(
+                len,
+                &"fewer elements in sequence",
+            ));
+        }
+        Ok(value)
+    }
+
+    fn visit_map<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        unsafe { helper::try_check_stack(self.ptr, 3)? };
+        let _g = StackGuard::new(self.ptr);
+        unsafe { sys::lua_pushnil(self.ptr) };
+        visitor.visit_map(MapDeserializer { de: &self })
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.lua_type() {
+            sys::LUA_TNIL => visitor.visit_unit(),
+            sys::LUA_TBOOLEAN => {
+                visitor.visit_bool(unsafe { sys::lua_toboolean(self.ptr, self.idx) } != 0)
+            }
+            sys::LUA_TNUMBER => {
+                let n = unsafe { sys::lua_tonumber(self.ptr, self.idx) };
+                match integer_from_number::<i64>(n) {
+                    Some(i) => visitor.visit_i64(i),
+                    None => visitor.visit_f64(n),
+                }
+            }
+            sys::LUA_TSTRING => {
+                let bytes = self.to_bytes().unwrap_or_default();
+                match std::str::from_utf8(bytes) {
+                    Ok(s) => visitor.visit_str(s),
+                    Err(_) => visitor.visit_bytes(bytes),
+                }
+            }
+            sys::LUA_TTABLE => match self.array_len()? {
+                Some((count, max)) if count > 0 => {
+                    let len = self.seq_len(count, max)?;
+                    self.visit_seq(len, visitor)
+                }
+                _ => self.visit_map(visitor),
+            },
+            _ if self.is_none() => visitor.visit_unit(),
+            _ => Err(self.unexpected("serializable value")),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.to_bytes().map(std::str::from_utf8) {
+            Some(Ok(s)) => visitor.visit_str(s),
+            Some(Err(e)) => Err(Error::Utf8Error(e)),
+            None => Err(self.unexpected("string")),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.to_bytes() {
+            Some(bytes) => visitor.visit_bytes(bytes),
+            None => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_none() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_none() {
+            visitor.visit_unit()
+        } else {
+            Err(self.unexpected("nil"))
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.lua_type() != sys::LUA_TTABLE {
+            return Err(self.unexpected("sequence"));
+        }
+        match self.array_len()? {
+            Some((count, max)) => {
+                let len = self.seq_len(count, max)?;
+                self.visit_seq(len, visitor)
+            }
+            None => Err(self.unexpected("sequence")),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.lua_type() != sys::LUA_TTABLE {
+            return Err(self.unexpected("map"));
+        }
+        self.visit_map(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if self.lua_type() != sys::LUA_TTABLE {
+            return Err(self.unexpected("struct"));
+        }
+        unsafe { helper::try_check_stack(self.ptr, 2)? };
+        let _g = StackGuard::new(self.ptr);
+        visitor.visit_map(StructDeserializer {
+            de: &self,
+            fields: fields.iter(),
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if let Some(bytes) = self.to_bytes() {
+            let variant = std::str::from_utf8(bytes)?;
+            return visitor.visit_enum(variant.into_deserializer());
+        }
+
+        if self.lua_type() != sys::LUA_TTABLE || self.array_len()? == Some((0, 0)) {
+            return Err(self.unexpected("enum"));
+        }
+        unsafe {
+            helper::try_check_stack(self.ptr, 3)?;
+            let _g = StackGuard::new(self.ptr);
+            sys::lua_pushnil(self.ptr);
+            sys::lua_next(self.ptr, self.idx);
+            sys::lua_pushvalue(self.ptr, -2);
+            if sys::lua_next(self.ptr, self.idx) != 0 {
+                return Err(Error::DeserializeError(
+                    "enum table must have exactly one entry".into(),
+                ));
+            }
+            let top = sys::lua_gettop(self.ptr);
+            visitor.visit_enum(EnumDeserializer {
+                key: Deserializer::new(self.ptr, top - 1, self.options),
+                value: Deserializer::new(self.ptr, top, self.options),
+            })
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    ::serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
+    }
+}
+
+struct SeqDeserializer<'a> {
+    de: &'a Deserializer,
+    index: usize,
+    len: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for &mut SeqDeserializer<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.index >= self.len {
+            return Ok(None);
+        }
+        self.index += 1;
+
+        let ptr = self.de.ptr;
+        unsafe {
+            helper::try_check_stack(ptr, 1)?;
+            let _g = StackGuard::new(ptr);
+            sys::lua_rawgeti_(ptr, self.de.idx, self.index as _);
+            let de = Deserializer::new(ptr, sys::lua_gettop(ptr), self.de.options);
+            seed.deserialize(de).map(Some)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.index)
+    }
+}
+
+struct MapDeserializer<'a> {
+    de: &'a Deserializer,
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let ptr = self.de.ptr;
+        unsafe {
+            if sys::lua_next(ptr, self.de.idx) == 0 {
+                return Ok(None);
+            }
+            let key = Deserializer::new(ptr, sys::lua_gettop(ptr) - 1, self.de.options);
+            seed.deserialize(key).map(Some)
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let ptr = self.de.ptr;
+        unsafe {
+            let top = sys::lua_gettop(ptr);
+            let value = seed.deserialize(Deserializer::new(ptr, top, self.de.options));
+            sys::lua_settop(ptr, top - 1);
+            value
+        }
+    }
+}
+
+struct StructDeserializer<'a> {
+    de: &'a Deserializer,
+    fields: std::slice::Iter<'static, &'static str>,
+}
+
+impl<'de> de::MapAccess<'de> for StructDeserializer<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let ptr = self.de.ptr;
+        for field in self.fields.by_ref() {
+            unsafe {
+                sys::lua_pushlstring_(ptr, field.as_ptr() as _, field.len());
+                sys::lua_rawget(ptr, self.de.idx);
+                if sys::lua_type(ptr, -1) == sys::LUA_TNIL {
+                    sys::lua_pop(ptr, 1);
+                    continue;
+                }
+            }
+            return seed.deserialize(field.into_deserializer()).map(Some);
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let ptr = self.de.ptr;
+        unsafe {
+            let top = sys::lua_gettop(ptr);
+            let value = seed.deserialize(Deserializer::new(ptr, top, self.de.options));
+            sys::lua_settop(ptr, top - 1);
+            value
+        }
+    }
+}
+
+struct EnumDeserializer {
+    key: Deserializer,
+    value: Deserializer,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = Deserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Deserializer), Error> {
+        let variant = seed.deserialize(self.key)?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
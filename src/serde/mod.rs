@@ -0,0 +1,146 @@
+mod de;
+mod ser;
+
+use std::fmt::Display;
+
+use crate::{
+    Mode,
+    error::Error,
+    from_lua::FromLua,
+    helper,
+    lua::{Lua, ValueArg},
+    stack_guard::StackGuard,
+    sys,
+    to_lua::ToLua,
+    value::{Value, ValueAccess, ValueRef, ValueState},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoneRepr {
+    Nil,
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SparseArrays {
+    Fill,
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub none: NoneRepr,
+    pub sparse_arrays: SparseArrays,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            none: NoneRepr::Nil,
+            sparse_arrays: SparseArrays::Fill,
+        }
+    }
+}
+
+impl Options {
+    pub fn none(mut self, none: NoneRepr) -> Self {
+        self.none = none;
+        self
+    }
+
+    pub fn sparse_arrays(mut self, sparse_arrays: SparseArrays) -> Self {
+        self.sparse_arrays = sparse_arrays;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Null;
+
+unsafe impl ToLua for Null {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        unsafe { sys::lua_pushlightuserdata(ptr, std::ptr::null_mut()) };
+        Ok(())
+    }
+}
+
+unsafe impl FromLua for Null {
+    fn try_from_lua(ptr: *mut sys::lua_State, idx: i32) -> Result<Self, Error> {
+        if unsafe { is_null(ptr, idx) } {
+            Ok(Null)
+        } else {
+            Err(Error::UnexpectedType)
+        }
+    }
+}
+
+unsafe impl ValueArg for Null {}
+
+pub(crate) unsafe fn is_null(ptr: *mut sys::lua_State, idx: i32) -> bool {
+    unsafe {
+        sys::lua_type(ptr, idx) == sys::LUA_TLIGHTUSERDATA
+            && sys::lua_touserdata(ptr, idx).is_null()
+    }
+}
+
+impl ::serde::ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::SerializeError(msg.to_string())
+    }
+}
+
+impl ::serde::de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::DeserializeError(msg.to_string())
+    }
+}
+
+struct Serialized<'a, T: ?Sized>(&'a T, Options);
+
+unsafe impl<T> ToLua for Serialized<'_, T>
+where
+    T: ::serde::Serialize + ?Sized,
+{
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        StackGuard::scope(ptr, || self.0.serialize(ser::Serializer::new(ptr, self.1)))
+    }
+}
+
+pub fn to_value<T>(lua: &Lua, value: &T) -> Result<ValueRef, Error>
+where
+    T: ::serde::Serialize + ?Sized,
+{
+    to_value_with(lua, value, Options::default())
+}
+
+pub fn to_value_with<T>(lua: &Lua, value: &T, options: Options) -> Result<ValueRef, Error>
+where
+    T: ::serde::Serialize + ?Sized,
+{
+    lua.try_create_value_ref(Serialized(value, options))
+}
+
+pub fn from_value<T, M>(value: &Value<M>) -> Result<T, Error>
+where
+    T: ::serde::de::DeserializeOwned,
+    M: Mode + ValueState,
+    M::State: ValueAccess,
+{
+    from_value_with(value, Options::default())
+}
+
+pub fn from_value_with<T, M>(value: &Value<M>, options: Options) -> Result<T, Error>
+where
+    T: ::serde::de::DeserializeOwned,
+    M: Mode + ValueState,
+    M::State: ValueAccess,
+{
+    let ptr = value.try_state()?;
+    unsafe {
+        helper::try_check_stack(ptr, 1)?;
+        let _g = StackGuard::new(ptr);
+        value.push(ptr);
+        let idx = sys::lua_gettop(ptr);
+        T::deserialize(de::Deserializer::new(ptr, idx, options))
+    }
+}
@@ -0,0 +1,395 @@
+use ::serde::ser::{self, Serialize};
+
+use crate::{error::Error, helper, sys, to_lua::ToLua};
+
+use super::{NoneRepr, Options};
+
+pub(crate) struct Serializer {
+    ptr: *mut sys::lua_State,
+    options: Options,
+}
+
+impl Serializer {
+    pub(crate) fn new(ptr: *mut sys::lua_State, options: Options) -> Self {
+        Self { ptr, options }
+    }
+
+    fn push<T: ToLua>(self, value: T) -> Result<(), Error> {
+        value.try_to_lua(self.ptr)
+    }
+
+    fn push_none(self) -> Result<(), Error> {
+        unsafe {
+            helper::try_check_stack(self.ptr, 1)?;
+            match self.options.none {
+                NoneRepr::Nil => sys::lua_pushnil(self.ptr),
+                NoneRepr::Null => sys::lua_pushlightuserdata(self.ptr, std::ptr::null_mut()),
+            }
+        }
+        Ok(())
+    }
+
+    fn push_table(&self, narr: usize, nrec: usize) -> Result<(), Error> {
+        unsafe {
+            helper::try_check_stack(self.ptr, 1)?;
+            sys::lua_createtable(self.ptr, narr as _, nrec as _);
+        }
+        Ok(())
+    }
+
+    fn push_variant_table(&self, variant: &str, narr: usize, nrec: usize) -> Result<(), Error> {
+        self.push_table(0, 1)?;
+        unsafe {
+            helper::try_check_stack(self.ptr, 2)?;
+            sys::lua_pushlstring_(self.ptr, variant.as_ptr() as _, variant.len());
+        }
+        self.push_table(narr, nrec)
+    }
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = SerializeSeq;
+    type SerializeTuple = SerializeSeq;
+    type SerializeTupleStruct = SerializeSeq;
+    type SerializeTupleVariant = SerializeSeq;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.push(v.encode_utf8(&mut [0; 4]) as &str)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.push_none()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.push_none()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.push_none()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.push(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let ptr = self.ptr;
+        self.push_table(0, 1)?;
+        unsafe {
+            helper::try_check_stack(ptr, 1)?;
+            sys::lua_pushlstring_(ptr, variant.as_ptr() as _, variant.len());
+            value.serialize(Serializer::new(ptr, self.options))?;
+            sys::lua_rawset(ptr, -3);
+        }
+        Ok(())
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeSeq, Error> {
+        self.push_table(len.unwrap_or(0), 0)?;
+        Ok(SerializeSeq::new(self, false))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeSeq, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeSeq, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeSeq, Error> {
+        self.push_variant_table(variant, len, 0)?;
+        Ok(SerializeSeq::new(self, true))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap, Error> {
+        self.push_table(0, len.unwrap_or(0))?;
+        Ok(SerializeMap::new(self, false))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeMap, Error> {
+        self.push_variant_table(variant, 0, len)?;
+        Ok(SerializeMap::new(self, true))
+    }
+}
+
+pub(crate) struct SerializeSeq {
+    ptr: *mut sys::lua_State,
+    options: Options,
+    index: i32,
+    variant: bool,
+}
+
+impl SerializeSeq {
+    fn new(serializer: Serializer, variant: bool) -> Self {
+        Self {
+            ptr: serializer.ptr,
+            options: serializer.options,
+            index: 0,
+            variant,
+        }
+    }
+
+    fn push_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(Serializer::new(self.ptr, self.options))?;
+        self.index += 1;
+        unsafe { sys::lua_rawseti_(self.ptr, -2, self.index) };
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.variant {
+            unsafe { sys::lua_rawset(self.ptr, -3) };
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SerializeSeq {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push_element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeSeq {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push_element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeSeq {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push_element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeSeq {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push_element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+pub(crate) struct SerializeMap {
+    ptr: *mut sys::lua_State,
+    options: Options,
+    variant: bool,
+}
+
+impl SerializeMap {
+    fn new(serializer: Serializer, variant: bool) -> Self {
+        Self {
+            ptr: serializer.ptr,
+            options: serializer.options,
+            variant,
+        }
+    }
+
+    fn push_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        key.serialize(Serializer::new(self.ptr, self.options))?;
+        unsafe {
+            let ty = sys::lua_type(self.ptr, -1);
+            let is_nan = ty == sys::LUA_TNUMBER && sys::lua_tonumber(self.ptr, -1).is_nan();
+            if ty == sys::LUA_TNIL || is_nan {
+                sys::lua_pop(self.ptr, 1);
+                return Err(Error::SerializeError("map key cannot be nil or NaN".into()));
+            }
+        }
+        Ok(())
+    }
+
+    fn push_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(Serializer::new(self.ptr, self.options))?;
+        unsafe { sys::lua_rawset(self.ptr, -3) };
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.variant {
+            unsafe { sys::lua_rawset(self.ptr, -3) };
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.push_key(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push_key(key)?;
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push_key(key)?;
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
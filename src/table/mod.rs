@@ -7,7 +7,7 @@ use std::{
 };
 
 use crate::{
-    Borrowed, Mode, Owned,
+    Borrowed, Mode, Owned, Weak,
     error::{Error, UnwrapDisplay},
     from_lua::FromLua,
     helper,
@@ -17,6 +17,7 @@ use crate::{
     prelude::{OwnedValue, TableView},
     sys,
     to_lua::ToLua,
+    weak::WeakSlot,
 };
 
 pub mod builder;
@@ -138,8 +139,52 @@ impl TableAccess for OwnedState {
     }
 }
 
+#[derive(Debug)]
+pub struct WeakState {
+    slot: WeakSlot,
+    table_ptr: *const std::ffi::c_void,
+}
+
+impl WeakState {
+    fn try_push(&self) -> Result<(*mut sys::lua_State, i32, TableView<'_>), Error> {
+        unsafe {
+            let ptr = self.slot.try_state()?;
+            helper::try_check_stack(ptr, 3)?;
+            let top = sys::lua_gettop(ptr);
+            if !self.slot.push(ptr) {
+                sys::lua_settop(ptr, top);
+                return Err(Error::ValueCollected);
+            }
+            let table_idx = sys::lua_absindex(ptr, -1);
+            Ok((ptr, top, TableView::new(ptr, table_idx)))
+        }
+    }
+}
+
+impl TableStorage for Weak {
+    type State = WeakState;
+}
+
+impl TableAccess for WeakState {
+    #[inline]
+    unsafe fn get_table_ptr(&self) -> *const std::ffi::c_void {
+        self.table_ptr
+    }
+
+    fn try_as_ref<'t>(&'t self) -> Result<Guard<'t>, Error> {
+        let (ptr, top, view) = self.try_push()?;
+        Ok(Guard(ptr, top, view))
+    }
+
+    fn try_as_mut<'t>(&'t mut self) -> Result<GuardMut<'t>, Error> {
+        let (ptr, top, view) = self.try_push()?;
+        Ok(GuardMut(ptr, top, view))
+    }
+}
+
 pub type StackTable = Table<Borrowed>;
 pub type TableRef = Table<Owned>;
+pub type WeakTableRef = Table<Weak>;
 
 #[derive(Debug)]
 #[repr(transparent)]
@@ -386,6 +431,52 @@ impl TableRef {
         let state = OwnedState { id, lua, table_ptr };
         Ok(Self { state })
     }
+
+    pub fn try_downgrade(&self) -> Result<WeakTableRef, Error> {
+        let lua = self.state.lua.borrow().clone();
+        let ptr = lua.try_state()?;
+        unsafe {
+            helper::try_check_stack(ptr, 1)?;
+            sys::lua_rawgeti(ptr, sys::LUA_REGISTRYINDEX, self.state.id as _);
+            let slot = WeakSlot::try_new(lua, ptr, -1);
+            sys::lua_pop(ptr, 1);
+
+            let table_ptr = self.state.table_ptr;
+            Ok(WeakTableRef {
+                state: WeakState {
+                    slot: slot?,
+                    table_ptr,
+                },
+            })
+        }
+    }
+
+    pub fn downgrade(&self) -> WeakTableRef {
+        self.try_downgrade().unwrap_display()
+    }
+}
+
+impl WeakTableRef {
+    pub fn upgrade(&self) -> Option<TableRef> {
+        self.state
+            .slot
+            .try_with_target(|ptr| Ok(TableRef::from_stack(ptr, -1)))
+            .ok()
+    }
+
+    pub fn try_clone(&self) -> Result<WeakTableRef, Error> {
+        let slot = self.state.slot.try_clone()?;
+        let table_ptr = self.state.table_ptr;
+        Ok(Self {
+            state: WeakState { slot, table_ptr },
+        })
+    }
+}
+
+impl Clone for WeakTableRef {
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_display()
+    }
 }
 
 impl Clone for TableRef {
@@ -0,0 +1,379 @@
+use std::{
+    cell::RefCell,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use crate::{
+    Borrowed, Mode, Owned,
+    error::{Error, UnwrapDisplay},
+    from_lua::FromLua,
+    func::FnRef,
+    helper, hook,
+    is_type::IsType,
+    lua::{self, InnerLua, ValueArg},
+    owned_value::LuaInnerHandle,
+    prelude::OwnedValue,
+    stack_guard::StackGuard,
+    sys,
+    to_lua::ToLua,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ThreadStatus {
+    Suspended,
+    Running,
+    Normal,
+    Dead,
+}
+
+pub trait ThreadState {
+    type State;
+}
+
+pub trait ThreadAccess {
+    fn try_ptr(&self) -> Result<*mut sys::lua_State, Error>;
+
+    fn thread_ptr(&self) -> *mut sys::lua_State;
+
+    fn try_status(&self) -> Result<ThreadStatus, Error> {
+        let ptr = self.try_ptr()?;
+        let co = self.thread_ptr();
+
+        if ptr == co {
+            return Ok(ThreadStatus::Running);
+        }
+
+        unsafe {
+            match sys::lua_status(co) {
+                sys::LUA_YIELD => Ok(ThreadStatus::Suspended),
+                sys::LUA_OK => {
+                    let mut ar: sys::lua_Debug = std::mem::zeroed();
+                    if sys::lua_getstack(co, 0, &mut ar) > 0 {
+                        Ok(ThreadStatus::Normal)
+                    } else if sys::lua_gettop(co) == 0 {
+                        Ok(ThreadStatus::Dead)
+                    } else {
+                        Ok(ThreadStatus::Suspended)
+                    }
+                }
+                _ => Ok(ThreadStatus::Dead),
+            }
+        }
+    }
+
+    fn try_resume<I: ToLua, O: FromLua + ValueArg>(&self, args: I) -> Result<O, Error> {
+        if self.try_status()? != ThreadStatus::Suspended {
+            return Err(Error::CoroutineNotResumable);
+        }
+
+        unsafe {
+            let ptr = self.try_ptr()?;
+            let co = self.thread_ptr();
+            helper::try_check_stack(co, I::LEN)?;
+            let _g = StackGuard::new(ptr);
+
+            let base = sys::lua_gettop(co);
+            args.try_to_lua_unchecked(co)?;
+            let nargs = sys::lua_gettop(co) - base;
+
+            let enforced = lua::memory::set_enforced(co, true);
+            hook::enter(co);
+            let status = sys::lua_resume_(co, nargs);
+            hook::leave(co);
+            lua::memory::set_enforced(co, enforced);
+
+            match status {
+                status if lua::memory::limit_exceeded(co, status) => {
+                    sys::lua_pop(co, 1);
+                    Err(Error::MemoryLimitExceeded)
+                }
+                sys::LUA_OK | sys::LUA_YIELD => {
+                    let nres = sys::lua_gettop(co);
+                    helper::try_check_stack(ptr, nres.max(O::LEN))?;
+                    sys::lua_xmove(co, ptr, nres);
+                    if O::VARIADIC {
+                        O::try_from_lua(ptr, sys::lua_gettop(ptr) - nres + 1)
+                    } else {
+                        sys::lua_settop(ptr, sys::lua_gettop(ptr) - nres + O::LEN);
+                        O::try_from_lua(ptr, -O::LEN)
+                    }
+                }
+                _ => {
+                    let frames = helper::collect_frames(co, 0);
+                    let err = Error::from_stack(co, -1).with_traceback(frames);
+                    sys::lua_pop(co, 1);
+                    Err(err)
+                }
+            }
+        }
+    }
+}
+
+pub struct BorrowedState {
+    ptr: *mut sys::lua_State,
+    idx: i32,
+    thread_ptr: *mut sys::lua_State,
+}
+
+impl ThreadState for Borrowed {
+    type State = BorrowedState;
+}
+
+impl ThreadAccess for BorrowedState {
+    fn try_ptr(&self) -> Result<*mut sys::lua_State, Error> {
+        Ok(self.ptr)
+    }
+
+    fn thread_ptr(&self) -> *mut sys::lua_State {
+        self.thread_ptr
+    }
+}
+
+#[derive(Debug)]
+pub struct OwnedState {
+    lua: RefCell<Rc<InnerLua>>,
+    id: i32,
+    thread_ptr: *mut sys::lua_State,
+}
+
+impl Drop for OwnedState {
+    fn drop(&mut self) {
+        if let Ok(ptr) = self.lua.borrow().try_state() {
+            unsafe { sys::luaL_unref(ptr, sys::LUA_REGISTRYINDEX, self.id) };
+        }
+    }
+}
+
+impl ThreadState for Owned {
+    type State = OwnedState;
+}
+
+impl ThreadAccess for OwnedState {
+    fn try_ptr(&self) -> Result<*mut sys::lua_State, Error> {
+        self.lua.borrow().try_state()
+    }
+
+    fn thread_ptr(&self) -> *mut sys::lua_State {
+        self.thread_ptr
+    }
+}
+
+pub type StackThread = Thread<Borrowed>;
+pub type ThreadRef = Thread<Owned>;
+
+pub struct Thread<M>
+where
+    M: Mode + ThreadState,
+    M::State: ThreadAccess,
+{
+    state: M::State,
+}
+
+impl<M> Thread<M>
+where
+    M: Mode + ThreadState,
+    M::State: ThreadAccess,
+{
+    #[inline]
+    pub fn try_status(&self) -> Result<ThreadStatus, Error> {
+        self.state.try_status()
+    }
+
+    #[inline]
+    pub fn status(&self) -> ThreadStatus {
+        self.try_status().unwrap_display()
+    }
+
+    #[inline]
+    pub fn is_resumable(&self) -> bool {
+        matches!(self.try_status(), Ok(ThreadStatus::Suspended))
+    }
+
+    #[inline]
+    pub fn resume<I: ToLua, O: FromLua + ValueArg>(&self, args: I) -> Result<O, Error> {
+        self.state.try_resume(args)
+    }
+}
+
+impl StackThread {
+    #[inline(always)]
+    pub fn try_to_owned(&self) -> Result<ThreadRef, Error> {
+        ThreadRef::try_from_lua(self.state.ptr, self.state.idx)
+    }
+
+    #[inline(always)]
+    pub fn to_owned(&self) -> ThreadRef {
+        self.try_to_owned().unwrap_display()
+    }
+}
+
+impl ThreadRef {
+    pub fn try_new(lua: Rc<InnerLua>, func: &FnRef) -> Result<ThreadRef, Error> {
+        unsafe {
+            let ptr = lua.try_state()?;
+            helper::try_check_stack(ptr, 2)?;
+            let _g = StackGuard::new(ptr);
+
+            let thread_ptr = sys::lua_newthread(ptr);
+            func.try_to_lua_unchecked(ptr)?;
+            sys::lua_xmove(ptr, thread_ptr, 1);
+
+            let id = sys::luaL_ref(ptr, sys::LUA_REGISTRYINDEX);
+            let lua = RefCell::new(lua);
+            Ok(Self {
+                state: OwnedState {
+                    lua,
+                    id,
+                    thread_ptr,
+                },
+            })
+        }
+    }
+
+    pub fn new(lua: Rc<InnerLua>, func: &FnRef) -> ThreadRef {
+        Self::try_new(lua, func).unwrap_display()
+    }
+
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        let lua = self.state.lua.clone();
+        let thread_ptr = self.state.thread_ptr;
+        let id = unsafe {
+            let ptr = lua.borrow().try_state()?;
+            sys::lua_rawgeti(ptr, sys::LUA_REGISTRYINDEX, self.state.id as _);
+            sys::luaL_ref(ptr, sys::LUA_REGISTRYINDEX)
+        };
+        Ok(Self {
+            state: OwnedState {
+                lua,
+                id,
+                thread_ptr,
+            },
+        })
+    }
+}
+
+impl Clone for ThreadRef {
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_display()
+    }
+}
+
+unsafe impl FromLua for StackThread {
+    fn try_from_lua(ptr: *mut sys::lua_State, idx: i32) -> Result<Self, Error> {
+        unsafe {
+            let idx = sys::lua_absindex(ptr, idx);
+            if sys::lua_isthread(ptr, idx) != 0 {
+                let thread_ptr = sys::lua_tothread(ptr, idx);
+                Ok(StackThread {
+                    state: BorrowedState {
+                        ptr,
+                        idx,
+                        thread_ptr,
+                    },
+                })
+            } else {
+                Err(Error::UnexpectedType)
+            }
+        }
+    }
+}
+
+unsafe impl FromLua for ThreadRef {
+    fn try_from_lua(ptr: *mut sys::lua_State, idx: i32) -> Result<Self, Error> {
+        unsafe {
+            let idx = sys::lua_absindex(ptr, idx);
+            if sys::lua_isthread(ptr, idx) != 0 {
+                helper::try_check_stack(ptr, 1)?;
+                let lua = RefCell::new(InnerLua::from_ptr(ptr));
+                let thread_ptr = sys::lua_tothread(ptr, idx);
+                sys::lua_pushvalue(ptr, idx);
+                let id = sys::luaL_ref(ptr, sys::LUA_REGISTRYINDEX);
+                Ok(ThreadRef {
+                    state: OwnedState {
+                        lua,
+                        id,
+                        thread_ptr,
+                    },
+                })
+            } else {
+                Err(Error::UnexpectedType)
+            }
+        }
+    }
+}
+
+unsafe impl ToLua for &StackThread {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        InnerLua::try_ensure_context_raw(self.state.ptr, ptr)?;
+        unsafe { sys::lua_pushvalue(ptr, self.state.idx) };
+        Ok(())
+    }
+}
+
+unsafe impl ToLua for StackThread {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        unsafe { (&self).try_to_lua_unchecked(ptr) }
+    }
+}
+
+unsafe impl ToLua for &ThreadRef {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        InnerLua::try_ensure_context_raw(self.state.lua.borrow().try_state()?, ptr)?;
+        unsafe { sys::lua_rawgeti(ptr, sys::LUA_REGISTRYINDEX, self.state.id as _) };
+        Ok(())
+    }
+}
+
+unsafe impl ToLua for ThreadRef {
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        unsafe { (&self).try_to_lua_unchecked(ptr) }
+    }
+}
+
+impl<M> IsType for Thread<M>
+where
+    M: Mode + ThreadState,
+    M::State: ThreadAccess,
+{
+    fn is_type(ptr: *mut sys::lua_State, idx: i32) -> bool {
+        unsafe { sys::lua_isthread(ptr, idx) != 0 }
+    }
+}
+
+impl<M1, M2> PartialEq<Thread<M2>> for Thread<M1>
+where
+    M1: Mode + ThreadState,
+    M1::State: ThreadAccess,
+    M2: Mode + ThreadState,
+    M2::State: ThreadAccess,
+{
+    fn eq(&self, other: &Thread<M2>) -> bool {
+        self.state.thread_ptr() == other.state.thread_ptr()
+    }
+}
+
+impl<M> Eq for Thread<M>
+where
+    M: Mode + ThreadState,
+    M::State: ThreadAccess,
+{
+}
+
+impl<M> Hash for Thread<M>
+where
+    M: Mode + ThreadState,
+    M::State: ThreadAccess,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.state.thread_ptr().hash(state);
+    }
+}
+
+impl crate::owned_value::private::Sealed for ThreadRef {}
+
+impl OwnedValue for ThreadRef {
+    fn handle(&self) -> LuaInnerHandle<'_> {
+        LuaInnerHandle(&self.state.lua)
+    }
+}
@@ -3,6 +3,7 @@ use std::cell::RefCell;
 use crate::{
     Nil,
     error::{Error, UnwrapDisplay},
+    from_lua::FromLua,
     stack_guard::StackGuard,
     sys,
 };
@@ -22,7 +23,7 @@ pub unsafe trait ToLua: Sized {
     fn try_to_lua(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
         unsafe {
             crate::helper::try_check_stack(ptr, Self::len())?;
-            self.to_lua_unchecked(ptr);
+            self.try_to_lua_unchecked(ptr)?;
         }
         Ok(())
     }
@@ -36,18 +37,30 @@ pub unsafe trait ToLua: Sized {
     }
 }
 
-unsafe impl ToLua for &i32 {
-    unsafe fn try_to_lua_unchecked(self, ptr: *mut crate::sys::lua_State) -> Result<(), Error> {
-        Ok(unsafe { sys::lua_pushinteger(ptr, (*self) as _) })
-    }
-}
+macro_rules! impl_to_lua_int {
+    ($($ty:ty),*) => {
+        $(
+            unsafe impl ToLua for &$ty {
+                unsafe fn try_to_lua_unchecked(self, ptr: *mut crate::sys::lua_State) -> Result<(), Error> {
+                    let n = *self as f64;
+                    if n as i128 != *self as i128 {
+                        return Err(Error::InvalidNumberConversion(self.to_string(), "lua number"));
+                    }
+                    Ok(unsafe { sys::lua_pushnumber(ptr, n) })
+                }
+            }
 
-unsafe impl ToLua for i32 {
-    unsafe fn try_to_lua_unchecked(self, ptr: *mut crate::sys::lua_State) -> Result<(), Error> {
-        unsafe { (&self).try_to_lua_unchecked(ptr) }
-    }
+            unsafe impl ToLua for $ty {
+                unsafe fn try_to_lua_unchecked(self, ptr: *mut crate::sys::lua_State) -> Result<(), Error> {
+                    unsafe { (&self).try_to_lua_unchecked(ptr) }
+                }
+            }
+        )*
+    };
 }
 
+impl_to_lua_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
 unsafe impl ToLua for &f32 {
     unsafe fn try_to_lua_unchecked(self, ptr: *mut crate::sys::lua_State) -> Result<(), Error> {
         Ok(unsafe { sys::lua_pushnumber(ptr, (*self) as _) })
@@ -106,6 +119,46 @@ unsafe impl ToLua for String {
     }
 }
 
+unsafe extern "C-unwind" fn property_index(ptr: *mut sys::lua_State) -> std::ffi::c_int {
+    unsafe {
+        sys::lua_settop(ptr, 2);
+        sys::lua_pushvalue(ptr, 2);
+        sys::lua_rawget(ptr, sys::lua_upvalueindex(1));
+
+        if sys::lua_isfunction(ptr, -1) != 0 {
+            sys::lua_pushvalue(ptr, 1);
+            sys::lua_call(ptr, 1, 1);
+            return 1;
+        }
+
+        sys::lua_pop(ptr, 1);
+        sys::lua_rawget(ptr, sys::lua_upvalueindex(2));
+        1
+    }
+}
+
+unsafe extern "C-unwind" fn property_newindex(ptr: *mut sys::lua_State) -> std::ffi::c_int {
+    unsafe {
+        sys::lua_settop(ptr, 3);
+        sys::lua_pushvalue(ptr, 2);
+        sys::lua_rawget(ptr, sys::lua_upvalueindex(1));
+
+        if sys::lua_isfunction(ptr, -1) != 0 {
+            sys::lua_pushvalue(ptr, 1);
+            sys::lua_pushvalue(ptr, 3);
+            sys::lua_call(ptr, 2, 0);
+            return 0;
+        }
+    }
+
+    unsafe {
+        crate::helper::catch(ptr, || {
+            let key = String::try_from_lua(ptr, 2).unwrap_or_else(|_| "?".into());
+            Err::<(), _>(Error::PropertyNotWritable(key))
+        })
+    }
+}
+
 unsafe impl<T> ToLua for T
 where
     T: UserData,
@@ -146,9 +199,28 @@ where
                 sys::lua_pushcclosure(ptr, __gc::<T>, 0);
                 sys::lua_setfield(ptr, mt_idx, c"__gc".as_ptr());
 
-                sys::lua_newtable(ptr);
-                sys::luaL_register(ptr, std::ptr::null(), methods.as_ptr());
-                sys::lua_setfield(ptr, mt_idx, c"__index".as_ptr());
+                let getters = T::getters();
+                let setters = T::setters();
+
+                if getters[0].name.is_null() && setters[0].name.is_null() {
+                    sys::lua_newtable(ptr);
+                    sys::luaL_register(ptr, std::ptr::null(), methods.as_ptr());
+                    sys::lua_setfield(ptr, mt_idx, c"__index".as_ptr());
+                } else {
+                    sys::lua_newtable(ptr);
+                    sys::luaL_register(ptr, std::ptr::null(), getters.as_ptr());
+                    sys::lua_newtable(ptr);
+                    sys::luaL_register(ptr, std::ptr::null(), methods.as_ptr());
+                    sys::lua_pushcclosure(ptr, property_index, 2);
+                    sys::lua_setfield(ptr, mt_idx, c"__index".as_ptr());
+
+                    sys::lua_newtable(ptr);
+                    sys::luaL_register(ptr, std::ptr::null(), setters.as_ptr());
+                    sys::lua_pushcclosure(ptr, property_newindex, 1);
+                    sys::lua_setfield(ptr, mt_idx, c"__newindex".as_ptr());
+                }
+
+                sys::luaL_register(ptr, std::ptr::null(), T::meta_functions().as_ptr());
             }
 
             sys::lua_setmetatable(ptr, -2);
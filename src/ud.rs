@@ -5,7 +5,7 @@ use std::{
 };
 
 use crate::{
-    Borrowed, Mode, Owned, UserData,
+    Borrowed, Mode, Owned, UserData, Weak,
     error::{Error, UnwrapDisplay},
     from_lua::FromLua,
     helper,
@@ -15,6 +15,7 @@ use crate::{
     prelude::OwnedValue,
     sys,
     to_lua::ToLua,
+    weak::WeakSlot,
 };
 
 pub trait UserDataState<T> {
@@ -137,13 +138,29 @@ where
     }
 }
 
+#[derive(Debug)]
+pub struct WeakState<T>
+where
+    T: UserData,
+{
+    slot: WeakSlot,
+    _marker: std::marker::PhantomData<*mut RefCell<T>>,
+}
+
+impl<T> UserDataState<T> for Weak
+where
+    T: UserData,
+{
+    type State = WeakState<T>;
+}
+
 pub type StackUd<T> = Ud<Borrowed, T>;
 pub type UdRef<T> = Ud<Owned, T>;
+pub type WeakUdRef<T> = Ud<Weak, T>;
 
 pub struct Ud<M, T>
 where
     M: Mode + UserDataState<T>,
-    M::State: UserDataAccess<T>,
 {
     state: M::State,
 }
@@ -231,6 +248,59 @@ where
             state: OwnedState { lua, id, ud_ptr },
         })
     }
+
+    pub fn try_downgrade(&self) -> Result<WeakUdRef<T>, Error> {
+        let lua = self.state.lua.borrow().clone();
+        let ptr = lua.try_state()?;
+        unsafe {
+            helper::try_check_stack(ptr, 1)?;
+            sys::lua_rawgeti(ptr, sys::LUA_REGISTRYINDEX, self.state.id as _);
+            let slot = WeakSlot::try_new(lua, ptr, -1);
+            sys::lua_pop(ptr, 1);
+
+            Ok(WeakUdRef {
+                state: WeakState {
+                    slot: slot?,
+                    _marker: std::marker::PhantomData,
+                },
+            })
+        }
+    }
+
+    pub fn downgrade(&self) -> WeakUdRef<T> {
+        self.try_downgrade().unwrap_display()
+    }
+}
+
+impl<T> WeakUdRef<T>
+where
+    T: UserData,
+{
+    pub fn upgrade(&self) -> Option<UdRef<T>> {
+        self.state
+            .slot
+            .try_with_target(|ptr| UdRef::<T>::try_from_lua(ptr, -1))
+            .ok()
+    }
+
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        let slot = self.state.slot.try_clone()?;
+        Ok(Self {
+            state: WeakState {
+                slot,
+                _marker: std::marker::PhantomData,
+            },
+        })
+    }
+}
+
+impl<T> Clone for WeakUdRef<T>
+where
+    T: UserData,
+{
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_display()
+    }
 }
 
 impl<T> Clone for UdRef<T>
@@ -1,11 +1,13 @@
 use std::{cell::RefCell, rc::Rc};
 
 use crate::{
-    Borrowed, Mode, Nil, Owned, UserData,
+    Borrowed, Mode, Nil, Owned, UserData, Weak,
+    cdata::{CDataRef, StackCData},
     error::{Error, UnwrapDisplay},
     from_lua::FromLua,
     func::{FnRef, StackFn},
     helper,
+    int64::LUA_TCDATA,
     lstr::{StackStr, StrRef},
     lua::InnerLua,
     owned_value::LuaInnerHandle,
@@ -13,8 +15,10 @@ use crate::{
     stack_guard::StackGuard,
     sys,
     table::{StackTable, TableRef},
+    thread::{StackThread, ThreadRef},
     to_lua::ToLua,
     ud::{StackUd, UdRef},
+    weak::WeakSlot,
 };
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -25,8 +29,9 @@ pub enum Kind {
     String,
     UserData,
     Func,
-    // Thread,
+    Thread,
     Table,
+    CData,
     Unknown,
 }
 
@@ -40,8 +45,9 @@ impl Kind {
                 sys::LUA_TSTRING => Ok(Kind::String),
                 sys::LUA_TUSERDATA => Ok(Kind::UserData),
                 sys::LUA_TFUNCTION => Ok(Kind::Func),
-                // sys::LUA_TTHREAD => {},
+                sys::LUA_TTHREAD => Ok(Kind::Thread),
                 sys::LUA_TTABLE => Ok(Kind::Table),
+                LUA_TCDATA => Ok(Kind::CData),
                 _ => Ok(Kind::Unknown),
             }
         }
@@ -184,6 +190,40 @@ pub trait ValueAccess {
     fn as_table(&self) -> TableRef {
         self.try_as_table().unwrap_display()
     }
+
+    fn try_with_thread<F: FnOnce(&StackThread) -> R, R>(&self, f: F) -> Result<R, Error>;
+
+    #[inline(always)]
+    fn with_thread<F: FnOnce(&StackThread) -> R, R>(&self, f: F) -> R {
+        self.try_with_thread(f).unwrap_display()
+    }
+
+    #[inline(always)]
+    fn try_as_thread(&self) -> Result<ThreadRef, Error> {
+        self.try_with_thread(|v| v.try_to_owned()).flatten()
+    }
+
+    #[inline(always)]
+    fn as_thread(&self) -> ThreadRef {
+        self.try_as_thread().unwrap_display()
+    }
+
+    fn try_with_cdata<F: FnOnce(&StackCData) -> R, R>(&self, f: F) -> Result<R, Error>;
+
+    #[inline(always)]
+    fn with_cdata<F: FnOnce(&StackCData) -> R, R>(&self, f: F) -> R {
+        self.try_with_cdata(f).unwrap_display()
+    }
+
+    #[inline(always)]
+    fn try_as_cdata(&self) -> Result<CDataRef, Error> {
+        self.try_with_cdata(|v| v.try_to_owned()).flatten()
+    }
+
+    #[inline(always)]
+    fn as_cdata(&self) -> CDataRef {
+        self.try_as_cdata().unwrap_display()
+    }
 }
 
 pub struct BorrowedState {
@@ -265,6 +305,20 @@ impl ValueAccess for BorrowedState {
             _ => Err(Error::UnexpectedType),
         }
     }
+
+    fn try_with_thread<F: FnOnce(&StackThread) -> R, R>(&self, f: F) -> Result<R, Error> {
+        match self.kind {
+            Kind::Thread => Ok(f(&StackThread::try_from_lua(self.ptr, self.idx)?)),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    fn try_with_cdata<F: FnOnce(&StackCData) -> R, R>(&self, f: F) -> Result<R, Error> {
+        match self.kind {
+            Kind::CData => Ok(f(&StackCData::try_from_lua(self.ptr, self.idx)?)),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
 }
 
 #[allow(unused)]
@@ -389,10 +443,159 @@ impl ValueAccess for OwnedState {
             _ => Err(Error::UnexpectedType),
         }
     }
+
+    fn try_with_thread<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&StackThread) -> R,
+    {
+        match self.kind {
+            Kind::Thread => self.with_value(|ptr| Ok(f(&StackThread::try_from_lua(ptr, -1)?))),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    fn try_with_cdata<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&StackCData) -> R,
+    {
+        match self.kind {
+            Kind::CData => self.with_value(|ptr| Ok(f(&StackCData::try_from_lua(ptr, -1)?))),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+}
+
+pub struct WeakState {
+    slot: WeakSlot,
+    kind: Kind,
+}
+
+impl WeakState {
+    fn with_value<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(*mut sys::lua_State) -> Result<R, Error>,
+    {
+        self.slot.try_with_target(f)
+    }
+}
+
+impl ValueState for Weak {
+    type State = WeakState;
+}
+
+impl ValueAccess for WeakState {
+    fn try_state(&self) -> Result<*mut mlua_sys::lua_State, Error> {
+        self.slot.try_state()
+    }
+
+    fn push(&self, ptr: *mut sys::lua_State) {
+        unsafe { self.slot.push(ptr) };
+    }
+
+    #[inline(always)]
+    fn is_stack_bound(&self) -> bool {
+        false
+    }
+
+    fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    fn try_with_nil<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(Nil) -> R,
+    {
+        match self.kind {
+            Kind::Nil => Ok(f(Nil)),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    fn try_with_bool<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(bool) -> R,
+    {
+        match self.kind {
+            Kind::Bool => self.with_value(|ptr| Ok(f(bool::try_from_lua(ptr, -1)?))),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    fn try_with_number<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(f64) -> R,
+    {
+        match self.kind {
+            Kind::Number => self.with_value(|ptr| Ok(f(f64::try_from_lua(ptr, -1)?))),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    fn try_with_str<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&StackStr) -> R,
+    {
+        match self.kind {
+            Kind::String => self.with_value(|ptr| Ok(f(&StackStr::try_from_lua(ptr, -1)?))),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    fn try_with_ud<T, F, R>(&self, f: F) -> Result<R, Error>
+    where
+        T: UserData,
+        F: FnOnce(&StackUd<T>) -> R,
+    {
+        match self.kind {
+            Kind::UserData => self.with_value(|ptr| Ok(f(&StackUd::<T>::try_from_lua(ptr, -1)?))),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    fn try_with_func<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&StackFn) -> R,
+    {
+        match self.kind {
+            Kind::Func => self.with_value(|ptr| Ok(f(&StackFn::try_from_lua(ptr, -1)?))),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    fn try_with_table<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&StackTable) -> R,
+    {
+        match self.kind {
+            Kind::Table => self.with_value(|ptr| Ok(f(&StackTable::try_from_lua(ptr, -1)?))),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    fn try_with_thread<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&StackThread) -> R,
+    {
+        match self.kind {
+            Kind::Thread => self.with_value(|ptr| Ok(f(&StackThread::try_from_lua(ptr, -1)?))),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
+
+    fn try_with_cdata<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&StackCData) -> R,
+    {
+        match self.kind {
+            Kind::CData => self.with_value(|ptr| Ok(f(&StackCData::try_from_lua(ptr, -1)?))),
+            _ => Err(Error::UnexpectedType),
+        }
+    }
 }
 
 pub type StackValue = Value<Borrowed>;
 pub type ValueRef = Value<Owned>;
+pub type WeakValueRef = Value<Weak>;
 
 pub struct Value<M>
 where
@@ -408,12 +611,12 @@ where
     M::State: ValueAccess,
 {
     #[inline(always)]
-    fn try_state(&self) -> Result<*mut mlua_sys::lua_State, Error> {
+    pub(crate) fn try_state(&self) -> Result<*mut mlua_sys::lua_State, Error> {
         self.state.try_state()
     }
 
     #[inline(always)]
-    fn push(&self, ptr: *mut sys::lua_State) {
+    pub(crate) fn push(&self, ptr: *mut sys::lua_State) {
         self.state.push(ptr);
     }
 
@@ -570,6 +773,46 @@ where
     pub fn as_table(&self) -> TableRef {
         self.state.as_table()
     }
+
+    #[inline(always)]
+    pub fn try_with_thread<F: FnOnce(&StackThread) -> R, R>(&self, f: F) -> Result<R, Error> {
+        self.state.try_with_thread(f)
+    }
+
+    #[inline(always)]
+    pub fn with_thread<F: FnOnce(&StackThread) -> R, R>(&self, f: F) -> R {
+        self.state.with_thread(f)
+    }
+
+    #[inline(always)]
+    pub fn try_as_thread(&self) -> Result<ThreadRef, Error> {
+        self.state.try_as_thread()
+    }
+
+    #[inline(always)]
+    pub fn as_thread(&self) -> ThreadRef {
+        self.state.as_thread()
+    }
+
+    #[inline(always)]
+    pub fn try_with_cdata<F: FnOnce(&StackCData) -> R, R>(&self, f: F) -> Result<R, Error> {
+        self.state.try_with_cdata(f)
+    }
+
+    #[inline(always)]
+    pub fn with_cdata<F: FnOnce(&StackCData) -> R, R>(&self, f: F) -> R {
+        self.state.with_cdata(f)
+    }
+
+    #[inline(always)]
+    pub fn try_as_cdata(&self) -> Result<CDataRef, Error> {
+        self.state.try_as_cdata()
+    }
+
+    #[inline(always)]
+    pub fn as_cdata(&self) -> CDataRef {
+        self.state.as_cdata()
+    }
 }
 
 impl StackValue {
@@ -610,6 +853,61 @@ impl ValueRef {
         let state = OwnedState { id, lua, kind };
         Ok(Self { state })
     }
+
+    pub fn try_downgrade(&self) -> Result<WeakValueRef, Error> {
+        let lua = self.state.lua.borrow().clone();
+        let ptr = lua.try_state()?;
+        unsafe {
+            helper::try_check_stack(ptr, 1)?;
+            sys::lua_rawgeti_(ptr, sys::LUA_REGISTRYINDEX, self.state.id);
+            let slot = WeakSlot::try_new(lua, ptr, -1);
+            sys::lua_pop(ptr, 1);
+
+            let kind = self.state.kind;
+            Ok(WeakValueRef {
+                state: WeakState { slot: slot?, kind },
+            })
+        }
+    }
+
+    pub fn downgrade(&self) -> WeakValueRef {
+        self.try_downgrade().unwrap_display()
+    }
+}
+
+impl WeakValueRef {
+    pub fn upgrade(&self) -> Option<ValueRef> {
+        if self.state.kind == Kind::Nil {
+            let ptr = self.state.slot.try_state().ok()?;
+            return unsafe {
+                helper::try_check_stack(ptr, 1).ok()?;
+                sys::lua_pushnil(ptr);
+                let value = ValueRef::try_from_stack(ptr, -1);
+                sys::lua_pop(ptr, 1);
+                value.ok()
+            };
+        }
+
+        self.state
+            .slot
+            .try_with_target(|ptr| ValueRef::try_from_stack(ptr, -1))
+            .ok()
+    }
+
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        let slot = self.state.slot.try_clone()?;
+        let kind = self.state.kind;
+        Ok(Self {
+            state: WeakState { slot, kind },
+        })
+    }
+}
+
+impl Clone for WeakValueRef {
+    #[inline]
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_display()
+    }
 }
 
 impl Clone for ValueRef {
@@ -687,7 +985,9 @@ where
             Kind::String => write!(f, "String({:?})", self.as_str().as_str()),
             Kind::Table => write!(f, "Table"),
             Kind::Func => write!(f, "Function"),
+            Kind::Thread => write!(f, "Thread"),
             Kind::UserData => write!(f, "UserData"),
+            Kind::CData => write!(f, "CData"),
             Kind::Unknown => write!(f, "Unknown"),
         }
     }
@@ -725,7 +1025,7 @@ where
             Kind::Bool => self.as_bool() == other.as_bool(),
             Kind::Number => self.as_number() == other.as_number(),
             Kind::String => self.with_str(|s| other.with_str(|os| s == os)),
-            Kind::UserData | Kind::Func => unsafe {
+            Kind::UserData | Kind::Func | Kind::Thread | Kind::CData => unsafe {
                 if !same_ctx {
                     return false;
                 }
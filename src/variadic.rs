@@ -0,0 +1,112 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::{
+    error::Error, from_lua::FromLua, helper, stack_guard::StackGuard, sys, to_lua::ToLua,
+    value::ValueRef,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Variadic<T>(pub Vec<T>);
+
+pub type MultiValue = Variadic<ValueRef>;
+
+impl<T> Variadic<T> {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for Variadic<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Variadic<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> From<Vec<T>> for Variadic<T> {
+    fn from(value: Vec<T>) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> FromIterator<T> for Variadic<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<T> IntoIterator for Variadic<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+unsafe impl<T: FromLua> FromLua for Variadic<T> {
+    const LEN: i32 = 0;
+    const VARIADIC: bool = true;
+
+    fn try_from_lua(ptr: *mut sys::lua_State, idx: i32) -> Result<Self, Error> {
+        let top = unsafe { sys::lua_gettop(ptr) };
+        let mut idx = if idx.is_negative() {
+            top + idx + 1
+        } else {
+            idx
+        };
+
+        let mut values = vec![];
+        while idx <= top {
+            values.push(T::try_from_lua(ptr, idx)?);
+            idx += T::LEN.max(1);
+        }
+        Ok(Variadic(values))
+    }
+}
+
+unsafe impl<T: ToLua> ToLua for Variadic<T> {
+    const LEN: i32 = 0;
+
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        unsafe {
+            helper::try_check_stack(ptr, self.0.len() as i32 * T::LEN)?;
+            let g = StackGuard::new(ptr);
+            for value in self.0 {
+                value.try_to_lua_unchecked(ptr)?;
+            }
+            g.commit();
+        }
+        Ok(())
+    }
+}
+
+unsafe impl<'a, T> ToLua for &'a Variadic<T>
+where
+    &'a T: ToLua,
+{
+    const LEN: i32 = 0;
+
+    unsafe fn try_to_lua_unchecked(self, ptr: *mut sys::lua_State) -> Result<(), Error> {
+        unsafe {
+            helper::try_check_stack(ptr, self.0.len() as i32 * <&T as ToLua>::LEN)?;
+            let g = StackGuard::new(ptr);
+            for value in &self.0 {
+                value.try_to_lua_unchecked(ptr)?;
+            }
+            g.commit();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{error::Error, helper, lua::InnerLua, stack_guard::StackGuard, sys};
+
+const WEAK_REGISTRY_KEY: usize = 0x6C6A72_05;
+
+static NEXT_WEAK_ID: AtomicU64 = AtomicU64::new(1);
+
+unsafe fn push_weak_registry(ptr: *mut sys::lua_State) {
+    unsafe {
+        sys::lua_pushlightuserdata(ptr, WEAK_REGISTRY_KEY as *mut std::ffi::c_void);
+        sys::lua_rawget(ptr, sys::LUA_REGISTRYINDEX);
+        if sys::lua_istable(ptr, -1) != 0 {
+            return;
+        }
+        sys::lua_pop(ptr, 1);
+
+        sys::lua_newtable(ptr);
+        sys::lua_newtable(ptr);
+        sys::lua_pushstring(ptr, c"v".as_ptr());
+        sys::lua_setfield(ptr, -2, c"__mode".as_ptr());
+        sys::lua_setmetatable(ptr, -2);
+
+        sys::lua_pushlightuserdata(ptr, WEAK_REGISTRY_KEY as *mut std::ffi::c_void);
+        sys::lua_pushvalue(ptr, -2);
+        sys::lua_rawset(ptr, sys::LUA_REGISTRYINDEX);
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct WeakSlot {
+    lua: RefCell<Rc<InnerLua>>,
+    id: u64,
+}
+
+impl WeakSlot {
+    pub(crate) fn try_new(
+        lua: Rc<InnerLua>,
+        ptr: *mut sys::lua_State,
+        idx: i32,
+    ) -> Result<Self, Error> {
+        unsafe {
+            helper::try_check_stack(ptr, 4)?;
+            let idx = sys::lua_absindex(ptr, idx);
+            let id = NEXT_WEAK_ID.fetch_add(1, Ordering::Relaxed);
+
+            push_weak_registry(ptr);
+            sys::lua_pushnumber(ptr, id as f64);
+            sys::lua_pushvalue(ptr, idx);
+            sys::lua_rawset(ptr, -3);
+            sys::lua_pop(ptr, 1);
+
+            let lua = RefCell::new(lua);
+            Ok(Self { lua, id })
+        }
+    }
+
+    pub(crate) fn try_state(&self) -> Result<*mut sys::lua_State, Error> {
+        self.lua.borrow().try_state()
+    }
+
+    pub(crate) unsafe fn push(&self, ptr: *mut sys::lua_State) -> bool {
+        unsafe {
+            push_weak_registry(ptr);
+            sys::lua_pushnumber(ptr, self.id as f64);
+            sys::lua_rawget(ptr, -2);
+            sys::lua_remove(ptr, -2);
+            sys::lua_isnil(ptr, -1) == 0
+        }
+    }
+
+    pub(crate) fn try_with_target<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(*mut sys::lua_State) -> Result<R, Error>,
+    {
+        unsafe {
+            let ptr = self.try_state()?;
+            helper::try_check_stack(ptr, 3)?;
+            let _g = StackGuard::new(ptr);
+            if !self.push(ptr) {
+                return Err(Error::ValueCollected);
+            }
+            f(ptr)
+        }
+    }
+
+    pub(crate) fn try_clone(&self) -> Result<Self, Error> {
+        let lua = self.lua.borrow().clone();
+        let ptr = lua.try_state()?;
+        unsafe {
+            helper::try_check_stack(ptr, 3)?;
+            let _g = StackGuard::new(ptr);
+            self.push(ptr);
+            Self::try_new(lua, ptr, -1)
+        }
+    }
+}
+
+impl Drop for WeakSlot {
+    fn drop(&mut self) {
+        if let Ok(ptr) = self.lua.borrow().try_state() {
+            unsafe {
+                if sys::lua_checkstack(ptr, 3) == 0 {
+                    return;
+                }
+                push_weak_registry(ptr);
+                sys::lua_pushnumber(ptr, self.id as f64);
+                sys::lua_pushnil(ptr);
+                sys::lua_rawset(ptr, -3);
+                sys::lua_pop(ptr, 1);
+            }
+        }
+    }
+}
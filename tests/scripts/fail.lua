@@ -0,0 +1,4 @@
+local function explode()
+    error('boom')
+end
+return explode()
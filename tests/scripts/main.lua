@@ -0,0 +1,3 @@
+local ui = require('ui')
+local button = require('ui.button')
+return ui.name .. ':' .. button.label
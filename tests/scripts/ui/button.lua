@@ -0,0 +1 @@
+return { label = 'ok' }
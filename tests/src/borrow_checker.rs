@@ -41,8 +41,7 @@ fn test_reentrancy() {
             return test:fail(test) == 20
             "#,
         );
-        let expected_msg = "cannot modify value";
-        assert!(matches!(result, Err(Error::LuaError(ref msg)) if msg.contains(expected_msg)));
+        assert_eq!(result, Err(Error::ValueLocked));
         assert_eq!(lua.top(), 0);
     }
 
@@ -118,7 +117,7 @@ fn test_callback_reentrancy() {
             "#,
         );
         let _ = redirect.into_inner();
-        let expected_msg = "cannot modify value";
+        let expected_msg = "ValueLocked";
         assert!(matches!(result, Err(Error::LuaError(ref msg)) if msg.contains(expected_msg)));
         assert_eq!(lua.top(), 0);
     }
@@ -176,7 +175,7 @@ fn test_callback_reentrancy_with_result() {
         r#"
         local sys = require 'sys'
         local err = sys:unsafe_run(function() sys:get_state() end)
-        return err == 'lua error: cannot modify value state: it is currently borrowed/in use'
+        return err == 'cannot modify value state: it is currently borrowed/in use'
         "#,
     );
     assert!(matches!(result, Ok(true)));
@@ -0,0 +1,42 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+static SCRIPTS: Bundle = include_lua!("scripts", check);
+
+#[test]
+fn test_bundle_files() {
+    let modules: Vec<_> = SCRIPTS.files().iter().map(|f| f.module()).collect();
+    assert_eq!(modules, ["fail", "main", "ui.button", "ui"]);
+
+    let button = SCRIPTS.get("ui.button").unwrap();
+    assert_eq!(button.path(), "scripts/ui/button.lua");
+    assert!(SCRIPTS.get("missing").is_none());
+}
+
+#[test]
+fn test_install_bundle() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+    lua.install_bundle(&SCRIPTS);
+
+    assert_eq!(
+        lua.do_string::<String>("return require('main')"),
+        Ok("ui:ok".into())
+    );
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_bundle_chunk_names() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+    lua.install_bundle(&SCRIPTS);
+
+    match lua.exec("require('fail')") {
+        Err(Error::LuaError(err)) => {
+            assert!(err.message.starts_with("scripts/fail.lua:2: boom"));
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,112 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*, value::Kind};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Vec3 {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+unsafe impl CType for Vec3 {
+    const NAME: &'static str = "struct Vec3";
+    const CDEF: &'static str = "struct Vec3 { double x, y, z; };";
+}
+
+#[test]
+fn test_cdata_kind_and_name() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.exec("n = require('ffi').new('int32_t', 42)").unwrap();
+    let data = lua.with_globals(|g| {
+        g.view("n", |v: &StackValue| {
+            assert_eq!(v.kind(), Kind::CData);
+            assert_eq!(v.to_owned().kind(), Kind::CData);
+            assert!(v.with_cdata(|c| c.type_name() == "int"));
+            v.as_cdata()
+        })
+    })
+    .unwrap();
+    assert_eq!(data.type_name(), "int");
+    assert_eq!(data.as_i64(), 42);
+    assert_eq!(data.as_f64(), 42.0);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_cdata_boxed_i64() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let data = lua
+        .do_string::<CDataRef>("return -9007199254740993LL")
+        .unwrap();
+    assert_eq!(data.type_name(), "int64_t");
+    assert_eq!(data.as_i64(), -9007199254740993);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_cdata_struct_roundtrip() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let v = lua.create_cdata(Vec3 {
+        x: 1.0,
+        y: 2.0,
+        z: 3.0,
+    });
+    assert_eq!(v.type_name(), "struct Vec3");
+    assert!(v.is::<Vec3>());
+
+    let mut globals = lua.globals();
+    globals.with_mut(|g| g.set("v", &v));
+    lua.exec("v.x = v.x + v.y + v.z").unwrap();
+    assert_eq!(v.read::<Vec3>().x, 6.0);
+
+    let mut scaled = lua
+        .do_string::<CDataRef>("local ffi = require('ffi') return ffi.new('struct Vec3', 4, 5, 6)")
+        .unwrap();
+    assert!(v != scaled);
+    scaled.with_mut(|v: &mut Vec3| v.z *= 2.0);
+    assert_eq!(scaled.with(|v: &Vec3| v.z), 12.0);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_cdata_pointer() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let v = lua.create_cdata(Vec3 {
+        x: 1.0,
+        y: 2.0,
+        z: 3.0,
+    });
+    let func = lua
+        .do_string::<FnRef>(
+            "local ffi = require('ffi') return function(v) return ffi.cast('struct Vec3 *', v) end",
+        )
+        .unwrap();
+    let p = func.call::<_, CDataRef>(&v).unwrap();
+    assert_eq!(p.type_name(), "struct Vec3 *");
+    assert_eq!(p.as_ptr::<Vec3>(), v.as_ptr::<Vec3>());
+    assert_eq!(unsafe { (*p.as_ptr::<Vec3>()).y }, 2.0);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_cdata_type_mismatch() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let data = lua
+        .do_string::<CDataRef>("return require('ffi').new('int32_t', 1)")
+        .unwrap();
+    assert!(!data.is::<Vec3>());
+    assert_eq!(data.try_read::<Vec3>(), Err(Error::UnexpectedType));
+    assert!(lua.do_string::<CDataRef>("return 1").is_err());
+    assert_eq!(lua.top(), 0);
+}
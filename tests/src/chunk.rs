@@ -0,0 +1,148 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+#[test]
+fn test_chunk_eval() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let result = lua.load("return 1 + 2").eval::<i32>();
+    assert_eq!(result, Ok(3));
+
+    let result = lua.load("x = 10").exec();
+    assert_eq!(result, Ok(()));
+    assert_eq!(lua.load("return x").eval::<i32>(), Ok(10));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_chunk_name() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let result = lua
+        .load("local a = 1\nerror('boom')")
+        .name("scripts/ai.lua")
+        .exec();
+    assert!(
+        matches!(result, Err(Error::LuaError(ref e)) if e.message == "scripts/ai.lua:2: boom")
+    );
+
+    let result = lua.load("return +").name("=config").exec();
+    assert!(matches!(result, Err(Error::InvalidSyntax(ref msg)) if msg.starts_with("config:1:")));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_chunk_env() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let mut env = lua.create_table();
+    env.with_mut(|t| t.set("value", 42));
+
+    let result = lua.load("leaked = true return value").env(env.clone()).eval::<i32>();
+    assert_eq!(result, Ok(42));
+    assert_eq!(env.with(|t| t.get::<_, bool>("leaked")), Some(true));
+    assert_eq!(lua.load("return leaked == nil").eval::<bool>(), Ok(true));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_chunk_into_function() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let func = lua
+        .load("counter = (counter or 0) + 1 return counter")
+        .name("counter.lua")
+        .into_function()
+        .unwrap();
+    assert_eq!(lua.load("return counter").eval::<Option<i32>>(), Ok(None));
+    assert_eq!(func.call::<_, i32>(()), Ok(1));
+    assert_eq!(func.call::<_, i32>(()), Ok(2));
+
+    let result = lua.load("return +").into_function();
+    assert!(matches!(result, Err(Error::InvalidSyntax(_))));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_chunk_mode() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let result = lua.load("return 1").mode(ChunkMode::Binary).eval::<i32>();
+    assert!(matches!(result, Err(Error::InvalidSyntax(_))));
+
+    let result = lua.load(b"\x1bLJ\x02").eval::<i32>();
+    assert!(matches!(result, Err(Error::InvalidSyntax(_))));
+
+    let result = lua.load("return 1").mode(ChunkMode::Both).eval::<i32>();
+    assert_eq!(result, Ok(1));
+
+    let bytecode = lua.load("return 1").into_function().unwrap().dump(false);
+    let result = lua.load(&bytecode).mode(ChunkMode::Both).eval::<i32>();
+    assert!(matches!(result, Err(Error::InvalidSyntax(_))));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_chunk_bytecode_round_trip() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let func = lua
+        .load("local a, b = ... return a * b")
+        .into_function()
+        .unwrap();
+    let bytecode = func.dump(false);
+    let stripped = func.dump(true);
+    assert!(stripped.len() <= bytecode.len());
+
+    let loaded = unsafe { lua.load_bytecode(&bytecode) }
+        .into_function()
+        .unwrap();
+    assert_eq!(loaded.call::<_, i32>((6, 7)), Ok(42));
+
+    let result = unsafe { lua.load_bytecode(&stripped) }.eval::<i32>();
+    assert!(matches!(result, Err(Error::LuaError(_))));
+
+    let other = Lua::new();
+    let loaded = unsafe { other.load_bytecode(&stripped) }
+        .into_function()
+        .unwrap();
+    assert_eq!(loaded.call::<_, i32>((2, 3)), Ok(6));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_chunk_bytecode_errors() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let native = lua.create_function(|_, ()| Ok(()));
+    assert!(native.try_dump(false).is_err());
+
+    let result = unsafe { lua.load_bytecode(b"return 1") }.eval::<i32>();
+    assert!(matches!(result, Err(Error::InvalidSyntax(_))));
+
+    let result = unsafe { lua.load_bytecode(b"\x1bLJ\x02garbage") }.eval::<i32>();
+    assert!(matches!(result, Err(Error::InvalidSyntax(_))));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_chunk_dump_on_bare_state() {
+    let lua = Lua::new();
+
+    let func = lua.load("return 5").into_function().unwrap();
+    let bytecode = func.dump(false);
+    let loaded = unsafe { lua.load_bytecode(&bytecode) }
+        .into_function()
+        .unwrap();
+    assert_eq!(loaded.call::<_, i32>(()), Ok(5));
+    assert!(matches!(func.try_dump(true), Err(Error::MissingGlobal(_))));
+    assert_eq!(lua.load("return string == nil").eval::<bool>(), Ok(true));
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,116 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+#[derive(Debug, PartialEq)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[user_data]
+impl Point {
+    fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    fn origin() -> Self {
+        Self { x: 0, y: 0 }
+    }
+
+    fn sum(&self) -> i32 {
+        self.x + self.y
+    }
+
+    fn translate(&mut self, dx: i32, dy: i32) {
+        self.x += dx;
+        self.y += dy;
+    }
+}
+
+#[test]
+fn test_register_type_constructor() {
+    let mut lua = Lua::new();
+    lua.register_type::<Point>("Point");
+
+    let result = lua.do_string::<i32>(
+        r#"
+        local p = Point.new(1, 2)
+        p:translate(3, 4)
+        return p:sum()
+        "#,
+    );
+    assert_eq!(result, Ok(10));
+
+    let result = lua.do_string::<UdRef<Point>>("return Point.origin()");
+    assert!(matches!(result, Ok(ref p) if *p.as_ref() == Point { x: 0, y: 0 }));
+    drop(result);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_register_type_only_static_functions() {
+    let mut lua = Lua::new();
+    lua.register_type::<Point>("Point");
+
+    let result = lua.do_string::<(bool, bool, bool)>(
+        "return Point.new ~= nil, Point.sum == nil, Point.translate == nil",
+    );
+    assert_eq!(result, Ok((true, true, true)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_register_type_wrong_args() {
+    let mut lua = Lua::new();
+    lua.register_type::<Point>("Point");
+
+    let result = lua.exec("Point.new('a', 2)");
+    assert!(matches!(result, Err(Error::ArgumentTypeMismatch(_, ref ty)) if ty.contains("i32")));
+
+    let result = lua.exec("Point:new(1, 2)");
+    assert!(matches!(result, Err(Error::ArgumentCountMismatch(..))));
+    assert_eq!(lua.top(), 0);
+}
+
+struct Counter {
+    value: i32,
+}
+
+#[user_data]
+impl Counter {
+    #[func]
+    fn new() -> Self {
+        Self { value: 0 }
+    }
+
+    #[func(name = "addOne")]
+    fn add_one(&mut self) {
+        self.value = self.step(self.value);
+    }
+
+    #[func(name = "getValue")]
+    fn value(&self) -> i32 {
+        self.value
+    }
+
+    fn step(&self, value: i32) -> i32 {
+        value + 1
+    }
+}
+
+#[test]
+fn test_func_attr_exports() {
+    let mut lua = Lua::new();
+    lua.register_type::<Counter>("Counter");
+
+    let result = lua.do_string::<(i32, bool, bool, bool)>(
+        r#"
+        local c = Counter.new()
+        c:addOne()
+        c:addOne()
+        return c:getValue(), c.step == nil, c.add_one == nil, c.value == nil
+        "#,
+    );
+    assert_eq!(result, Ok((2, true, true, true)));
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,183 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+#[derive(Debug, PartialEq, ToLua, FromLua)]
+struct Config {
+    name: String,
+    #[lua(rename = "maxPlayers")]
+    max_players: i32,
+    #[lua(default)]
+    verbose: bool,
+    #[lua(default = "default_port")]
+    port: i32,
+    motd: Option<String>,
+    #[lua(skip)]
+    cache: Vec<u8>,
+}
+
+fn default_port() -> i32 {
+    7777
+}
+
+#[derive(Debug, PartialEq, ToLua, FromLua)]
+struct Pair(i32, String);
+
+#[derive(Debug, PartialEq, ToLua, FromLua)]
+struct Scene {
+    title: String,
+    origin: Pair,
+}
+
+#[derive(Debug, Clone, PartialEq, ToLua, FromLua)]
+#[lua(tag = "kind")]
+enum Shape {
+    Empty,
+    #[lua(rename = "circle")]
+    Circle {
+        radius: f64,
+    },
+    Rect(f64, f64),
+}
+
+#[test]
+fn test_derive_struct_to_lua() {
+    let mut lua = Lua::new();
+    lua.with_globals_mut(|g| {
+        g.set(
+            "config",
+            Config {
+                name: "server".into(),
+                max_players: 8,
+                verbose: true,
+                port: 80,
+                motd: None,
+                cache: vec![1, 2, 3],
+            },
+        )
+    });
+
+    let result = lua.do_string::<(String, i32, bool, i32)>(
+        "return config.name, config.maxPlayers, config.verbose, config.port",
+    );
+    assert_eq!(result, Ok(("server".into(), 8, true, 80)));
+
+    let result = lua.do_string::<(bool, bool)>("return config.motd == nil, config.cache == nil");
+    assert_eq!(result, Ok((true, true)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_derive_struct_from_lua() {
+    let mut lua = Lua::new();
+
+    let result = lua.do_string::<Config>("return { name = 'server', maxPlayers = 4, motd = 'hi' }");
+    assert_eq!(
+        result,
+        Ok(Config {
+            name: "server".into(),
+            max_players: 4,
+            verbose: false,
+            port: 7777,
+            motd: Some("hi".into()),
+            cache: vec![],
+        })
+    );
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_derive_tuple_struct() {
+    let mut lua = Lua::new();
+    lua.with_globals_mut(|g| g.set("pair", Pair(1, "one".into())));
+
+    let result = lua.do_string::<(i32, String, i32)>("return pair[1], pair[2], #pair");
+    assert_eq!(result, Ok((1, "one".into(), 2)));
+
+    let result = lua.do_string::<Pair>("return { 2, 'two' }");
+    assert_eq!(result, Ok(Pair(2, "two".into())));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_derive_enum_round_trip() {
+    let mut lua = Lua::new();
+    let identity = lua
+        .do_string::<FnRef>("return function(v) return v end")
+        .unwrap();
+
+    for shape in [
+        Shape::Empty,
+        Shape::Circle { radius: 2.0 },
+        Shape::Rect(3.0, 4.0),
+    ] {
+        let result = identity.call::<_, Shape>(shape.clone());
+        assert_eq!(result, Ok(shape));
+    }
+
+    let result = lua.do_string::<(Shape, Shape, Shape)>(
+        "return 'Empty', { kind = 'circle', radius = 1 }, { kind = 'Rect', 5, 6 }",
+    );
+    assert_eq!(
+        result,
+        Ok((
+            Shape::Empty,
+            Shape::Circle { radius: 1.0 },
+            Shape::Rect(5.0, 6.0)
+        ))
+    );
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_derive_enum_to_lua_shape() {
+    let mut lua = Lua::new();
+    lua.with_globals_mut(|g| {
+        g.set("empty", Shape::Empty);
+        g.set("circle", Shape::Circle { radius: 2.0 });
+        g.set("rect", Shape::Rect(3.0, 4.0));
+    });
+
+    let result = lua.do_string::<(String, String, f64)>("return empty, circle.kind, circle.radius");
+    assert_eq!(result, Ok(("Empty".into(), "circle".into(), 2.0)));
+
+    let result = lua.do_string::<(String, f64)>("return rect.kind, rect[2]");
+    assert_eq!(result, Ok(("Rect".into(), 4.0)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_derive_unknown_variant() {
+    let mut lua = Lua::new();
+    let identity = lua
+        .do_string::<FnRef>("return function(v) return v end")
+        .unwrap();
+
+    let result = identity.call::<_, Shape>("Triangle");
+    assert_eq!(result, Err(Error::UnknownVariant("Triangle".into())));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_derive_error_field_path() {
+    let mut lua = Lua::new();
+    let identity = lua
+        .do_string::<FnRef>("return function(v) return v end")
+        .unwrap();
+
+    let table = lua
+        .do_string::<TableRef>("return { title = 'x', origin = { 1, false } }")
+        .unwrap();
+    let result = identity.call::<_, Scene>(&table);
+    assert_eq!(
+        result,
+        Err(Error::InvalidField(
+            "origin.2".into(),
+            Box::new(Error::UnexpectedType)
+        ))
+    );
+
+    let table = lua.do_string::<TableRef>("return { name = 'x' }").unwrap();
+    let result = identity.call::<_, Config>(&table);
+    assert!(matches!(result, Err(Error::InvalidField(ref path, _)) if path == "maxPlayers"));
+    assert_eq!(lua.top(), 0);
+}
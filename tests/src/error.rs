@@ -0,0 +1,143 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+#[test]
+fn test_error_table_value() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let result = lua.exec("error({ code = 42 })");
+    let Err(Error::LuaValue(value)) = result else {
+        panic!("expected lua value error");
+    };
+    let value = value.value().unwrap();
+    assert_eq!(value.as_table().with(|t| t.get::<_, i32>("code")), Some(42));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_error_nil_value() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let result = lua.exec("error()");
+    let Err(Error::LuaValue(value)) = result else {
+        panic!("expected lua value error");
+    };
+    assert!(value.value().unwrap().try_as_nil().is_ok());
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_error_raise_value_from_rust() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let fail = lua.create_function(|lua: &Lua, code: i32| -> Result<(), Error> {
+        let mut t = lua.create_table();
+        t.with_mut(|t| t.set("code", code));
+        Err(Error::LuaValue(lua.create_value_ref(t).into()))
+    });
+    lua.with_globals_mut(|g| g.set("fail", fail));
+
+    let result = lua.do_string::<i32>(
+        r#"
+        local ok, err = pcall(fail, 7)
+        return err.code
+        "#,
+    );
+    assert_eq!(result, Ok(7));
+
+    let result = lua.exec("fail(9)");
+    let Err(Error::LuaValue(value)) = result else {
+        panic!("expected lua value error");
+    };
+    let value = value.value().unwrap();
+    assert_eq!(value.as_table().with(|t| t.get::<_, i32>("code")), Some(9));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_error_round_trip_nested() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let inner = lua.create_function(|_, ()| -> Result<(), Error> {
+        Err(Error::PropertyNotWritable("hp".into()))
+    });
+    let outer = lua.create_function(|_, f: FnRef| f.call::<_, ()>(()));
+    lua.with_globals_mut(|g| {
+        g.set("inner", inner);
+        g.set("outer", outer);
+    });
+
+    let result = lua.exec("outer(function() inner() end)");
+    assert_eq!(result, Err(Error::PropertyNotWritable("hp".into())));
+
+    let result = lua.do_string::<bool>(
+        r#"
+        local ok, err = pcall(inner)
+        return not ok and tostring(err) == 'no writable property named hp'
+        "#,
+    );
+    assert_eq!(result, Ok(true));
+
+    let result = lua.exec("local ok, err = pcall(inner) error(err)");
+    assert_eq!(result, Err(Error::PropertyNotWritable("hp".into())));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_error_is_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Error>();
+
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let mut run = || -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        lua.exec("error({})")?;
+        Ok(())
+    };
+    let err = run().unwrap_err();
+    assert_eq!(err.to_string(), "lua error: Table");
+
+    let err = lua.exec("error({})").unwrap_err();
+    let handle = std::thread::spawn(move || {
+        let Error::LuaValue(value) = &err else {
+            panic!("expected lua value error");
+        };
+        assert!(value.value().is_none());
+        err.to_string()
+    });
+    assert_eq!(handle.join().unwrap(), "lua error: Table");
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_error_wrapped_behaves_like_string() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let inner = lua.create_function(|_, ()| -> Result<(), Error> {
+        Err(Error::PropertyNotWritable("hp".into()))
+    });
+    lua.with_globals_mut(|g| g.set("inner", inner));
+
+    let result = lua.do_string::<(bool, String, String, String)>(
+        r#"
+        local ok, err = pcall(inner)
+        return err:find('writable') ~= nil, 'error: ' .. err, err .. '!', err:upper()
+        "#,
+    );
+    assert_eq!(
+        result,
+        Ok((
+            true,
+            "error: no writable property named hp".into(),
+            "no writable property named hp!".into(),
+            "NO WRITABLE PROPERTY NAMED HP".into(),
+        ))
+    );
+    assert_eq!(lua.top(), 0);
+}
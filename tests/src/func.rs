@@ -328,3 +328,159 @@ fn test_integration_table_iter_func_call_with_stack_ud() {
     assert_eq!(total, 60);
     assert_eq!(lua.top(), 0);
 }
+
+#[test]
+fn test_create_function_from_closure() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let repeat = lua.create_function(|_, (s, n): (StrRef, i32)| Ok(s.as_str().repeat(n as usize)));
+    lua.with_globals_mut(|g| g.set("repeat_str", &repeat));
+
+    let result = lua.do_string::<String>("return repeat_str('ab', 3)");
+    assert_eq!(result, Ok("ababab".to_string()));
+
+    let result: Result<String, _> = repeat.call(("x", 2));
+    assert_eq!(result, Ok("xx".to_string()));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_create_function_captures_state() {
+    use std::{cell::Cell, rc::Rc};
+
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let counter = Rc::new(Cell::new(0));
+    let counter_clone = counter.clone();
+    let inc = lua.create_function(move |_, step: i32| {
+        counter_clone.set(counter_clone.get() + step);
+        Ok(counter_clone.get())
+    });
+    lua.with_globals_mut(|g| g.set("inc", inc));
+
+    let result = lua.do_string::<i32>("inc(1); inc(2); return inc(3)");
+    assert_eq!(result, Ok(6));
+    assert_eq!(counter.get(), 6);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_create_function_uses_lua() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let make = lua.create_function(|lua, ()| {
+        let mut t = lua.create_table();
+        t.with_mut(|t| t.set("value", 42));
+        Ok(t)
+    });
+    lua.with_globals_mut(|g| g.set("make", make));
+
+    let result = lua.do_string::<i32>("return make().value");
+    assert_eq!(result, Ok(42));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_create_function_errors() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let fail = lua.create_function(|_, v: i32| -> Result<(), Error> {
+        Err(Error::Generic(format!("bad value {}", v)))
+    });
+    let boom = lua.create_function(|_, ()| -> Result<(), Error> { panic!("boom") });
+    lua.with_globals_mut(|g| {
+        g.set("fail", fail);
+        g.set("boom", boom);
+    });
+
+    let result = lua.do_string::<()>("fail(7)");
+    assert_eq!(result, Err(Error::Generic("bad value 7".into())));
+
+    let result = lua.do_string::<()>("fail()");
+    assert_eq!(result, Err(Error::ArgumentCountMismatch(1, 0)));
+
+    let result = lua.do_string::<()>("boom()");
+    assert!(matches!(result, Err(Error::LuaError(ref msg)) if msg.contains("Rust panic: boom")));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_create_function_dropped_with_state() {
+    use std::rc::Rc;
+
+    let marker = Rc::new(());
+    {
+        let lua = Lua::new();
+        let marker_clone = marker.clone();
+        let _f = lua.create_function(move |_, ()| {
+            let _ = &marker_clone;
+            Ok(())
+        });
+        assert_eq!(Rc::strong_count(&marker), 2);
+    }
+    assert_eq!(Rc::strong_count(&marker), 1);
+}
+
+#[test]
+fn test_fn_ref_five_values() {
+    let mut lua = Lua::new();
+
+    let lua_fn = lua
+        .do_string::<FnRef>("return function(a, b) return a, b, a + b, a * b, 'done' end")
+        .unwrap();
+
+    let result: Result<(i32, i32, i32, i32, String), _> = lua_fn.call((2, 3));
+    assert_eq!(result, Ok((2, 3, 5, 6, "done".to_string())));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_fn_ref_max_tuple_arity() {
+    type TwentyFive = (
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+    );
+
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let values = lua
+        .do_string::<TwentyFive>("return 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25")
+        .unwrap();
+    assert_eq!((values.0, values.15, values.24), (1, 16, 25));
+
+    let lua_fn = lua
+        .do_string::<FnRef>("return function(...) return select('#', ...), ... end")
+        .unwrap();
+    let (count, result) = lua_fn.call::<_, (i32, TwentyFive)>(values).unwrap();
+    assert_eq!(count, 25);
+    assert_eq!((result.0, result.15, result.24), (1, 16, 25));
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,63 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+#[test]
+fn test_gc_collect() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.exec("data = {} for i = 1, 10000 do data[i] = { i } end")
+        .unwrap();
+    let before = lua.gc_count_bytes();
+    lua.exec("data = nil").unwrap();
+    lua.gc_collect();
+    assert!(lua.gc_count_bytes() < before);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_gc_stop_restart() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.gc_collect();
+    lua.gc_stop();
+    let before = lua.gc_count_bytes();
+    lua.exec("for i = 1, 10000 do local t = { i } end").unwrap();
+    let during = lua.gc_count_bytes();
+    assert!(during > before);
+
+    lua.gc_restart();
+    lua.gc_collect();
+    assert!(lua.gc_count_bytes() < during);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_gc_step() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.exec("for i = 1, 10000 do local t = { i } end").unwrap();
+    let mut finished = false;
+    for _ in 0..1000 {
+        if lua.gc_step(64) {
+            finished = true;
+            break;
+        }
+    }
+    assert!(finished);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_gc_tuning() {
+    let lua = Lua::new();
+
+    let pause = lua.gc_set_pause(150);
+    assert_eq!(lua.gc_set_pause(pause), 150);
+
+    let multiplier = lua.gc_set_step_multiplier(300);
+    assert_eq!(lua.gc_set_step_multiplier(multiplier), 300);
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,195 @@
+#![allow(unused)]
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use ljr::{Error, prelude::*};
+
+#[test]
+fn test_hook_instruction_limit() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.set_execution_limit(10_000u64);
+    let result = lua.exec("while true do end");
+    assert_eq!(result, Err(Error::ExecutionLimitExceeded));
+    assert_eq!(lua.top(), 0);
+
+    lua.remove_hook();
+    assert_eq!(
+        lua.do_string::<i32>("local n = 0 for i = 1, 100000 do n = n + 1 end return n"),
+        Ok(100000)
+    );
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_hook_duration_limit() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.set_execution_limit(Duration::from_millis(20));
+    let result = lua.exec("local x = 0 while true do x = x + 1 end");
+    assert_eq!(result, Err(Error::ExecutionLimitExceeded));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_hook_limit_resets_per_call() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.set_execution_limit(10_000u64);
+    let result = lua.exec("while true do end");
+    assert_eq!(result, Err(Error::ExecutionLimitExceeded));
+
+    for _ in 0..3 {
+        assert_eq!(
+            lua.do_string::<i32>("local n = 0 for i = 1, 100 do n = n + 1 end return n"),
+            Ok(100)
+        );
+    }
+
+    lua.set_execution_limit(Duration::from_millis(20));
+    let result = lua.exec("while true do end");
+    assert_eq!(result, Err(Error::ExecutionLimitExceeded));
+    assert_eq!(lua.do_string::<i32>("return 1 + 1"), Ok(2));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_hook_limit_on_bare_state() {
+    let mut lua = Lua::new();
+
+    lua.set_execution_limit(10_000u64);
+    assert_eq!(lua.do_string::<bool>("return jit == nil"), Ok(true));
+    assert_eq!(
+        lua.exec("while true do end"),
+        Err(Error::ExecutionLimitExceeded)
+    );
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_hook_limit_with_hot_traces() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.exec(
+        r#"
+        function spin(n)
+            local x = 0
+            for i = 1, n do x = x + i end
+            return x
+        end
+        for i = 1, 100 do spin(1000) end
+        "#,
+    )
+    .unwrap();
+
+    lua.set_execution_limit(10_000u64);
+    let result = lua.exec("spin(1e12)");
+    assert_eq!(result, Err(Error::ExecutionLimitExceeded));
+
+    lua.set_execution_limit(10_000u64);
+    let result = lua.exec("while true do pcall(function() while true do end end) end");
+    assert_eq!(result, Err(Error::ExecutionLimitExceeded));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_hook_limit_ignores_script_jit_state() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.exec(
+        r#"
+        function spin(n)
+            local x = 0
+            for i = 1, n do x = x + i end
+            return x
+        end
+        for i = 1, 100 do spin(1000) end
+        jit.status = function() return false end
+        package.loaded.jit = nil
+        "#,
+    )
+    .unwrap();
+
+    lua.set_execution_limit(10_000u64);
+    let result = lua.exec("spin(1e12)");
+    assert_eq!(result, Err(Error::ExecutionLimitExceeded));
+
+    lua.exec("jit.on() spin(1000)").unwrap();
+    let result = lua.exec("spin(1e12)");
+    assert_eq!(result, Err(Error::ExecutionLimitExceeded));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_hook_restores_jit_mode() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.set_execution_limit(10_000u64);
+    assert!(!lua.jit().status().enabled);
+    lua.remove_hook();
+    assert!(lua.jit().status().enabled);
+
+    lua.jit().set_enabled(false);
+    lua.set_execution_limit(10_000u64);
+    lua.remove_hook();
+    assert!(!lua.jit().status().enabled);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_hook_triggers() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let events = Rc::new(RefCell::new(vec![]));
+    let events_clone = events.clone();
+    lua.set_hook(
+        HookTriggers {
+            on_calls: true,
+            every_line: true,
+            ..Default::default()
+        },
+        move |_, event| {
+            events_clone.borrow_mut().push(event);
+            Ok(())
+        },
+    );
+
+    lua.load("local function f() end\nf()\nf()")
+        .name("=hook")
+        .exec()
+        .unwrap();
+    lua.remove_hook();
+
+    let events = events.borrow();
+    assert!(events.contains(&HookEvent::Line(2)));
+    assert!(events.contains(&HookEvent::Line(3)));
+    assert!(events.iter().filter(|e| **e == HookEvent::Call).count() >= 2);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_hook_callback_error() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.set_hook(
+        HookTriggers {
+            on_calls: true,
+            ..Default::default()
+        },
+        |_, _| Err(Error::Generic("no calls allowed".into())),
+    );
+
+    let result = lua.exec("local function f() end f()");
+    assert_eq!(result, Err(Error::Generic("no calls allowed".into())));
+
+    lua.remove_hook();
+    assert_eq!(lua.exec("local function f() end f()"), Ok(()));
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,61 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+#[test]
+fn test_jit_status() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let jit = lua.jit();
+    assert!(jit.status().enabled);
+
+    jit.set_enabled(false);
+    assert!(!jit.status().enabled);
+    assert_eq!(lua.do_string::<bool>("return jit.status()"), Ok(false));
+
+    jit.set_enabled(true);
+    assert!(jit.status().enabled);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_jit_opt_flags() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let jit = lua.jit();
+    jit.set_opt_flag("fold", false);
+    assert!(jit.status().flags.iter().all(|f| f != "fold"));
+
+    jit.set_opt_flag("fold", true);
+    assert!(jit.status().flags.iter().any(|f| f == "fold"));
+
+    jit.set_opt_level(0);
+    assert!(jit.status().flags.iter().all(|f| f != "fold"));
+
+    jit.set_opt_param("hotloop", 1);
+    jit.set_opt_param("maxtrace", 100);
+    assert!(jit.try_set_opt_param("unknown", 1).is_err());
+    assert!(jit.try_set_opt_flag("unknown", true).is_err());
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_jit_function_mode() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let func = lua
+        .do_string::<FnRef>(
+            "return function(n) local x = 0 for i = 1, n do x = x + i end return x end",
+        )
+        .unwrap();
+    let jit = lua.jit();
+    jit.set_function_enabled(&func, false);
+    assert_eq!(func.call::<_, i32>(100), Ok(5050));
+    jit.set_function_enabled(&func, true);
+    jit.flush_function(&func);
+    jit.flush();
+    assert_eq!(func.call::<_, i32>(100), Ok(5050));
+    assert_eq!(lua.top(), 0);
+}
@@ -1,12 +1,32 @@
 mod borrow_checker;
+mod bundle;
+mod cdata;
+mod chunk;
+mod class;
+mod derive;
+mod error;
 mod func;
+mod gc;
 mod global;
+mod hook;
+mod jit;
+mod loader;
+mod memory;
+mod meta;
+mod number;
 mod option;
+mod property;
 mod result;
 mod safety;
+mod sandbox;
+mod serde;
 mod str;
 mod table;
+mod thread;
+mod traceback;
 mod value;
+mod variadic;
+mod weak;
 
 #[cfg(test)]
 use ljr::{Error, prelude::*};
@@ -74,7 +94,7 @@ fn test_do_string_error() {
 
     let value = lua.do_string::<String>("error('error')");
     let expected_err_msg = r#"[string "error('error')"]:1: error"#.to_string();
-    assert_eq!(value, Err(Error::LuaError(expected_err_msg)));
+    assert!(matches!(value, Err(Error::LuaError(ref e)) if e.message == expected_err_msg));
     assert_eq!(lua.top(), 0);
 }
 
@@ -400,8 +420,7 @@ fn test_ud_fn_wrong_arg_count_error() {
     }
     lua.register("test", Test);
     let value = lua.do_string::<i32>("local test = require 'test'; return test.sum(10)");
-    let err_msg = "wrong number of arguments";
-    assert!(matches!(value, Err(Error::LuaError(msg)) if msg.contains(err_msg)));
+    assert_eq!(value, Err(Error::ArgumentCountMismatch(2, 1)));
     assert_eq!(lua.top(), 0);
 }
 
@@ -418,7 +437,7 @@ fn test_ud_fn_wrong_arg_type_error() {
     }
     lua.register("test", Test);
     let value = lua.do_string::<i32>("local test = require 'test'; return test.sum(10, 'hello')");
-    assert!(matches!(value, Err(Error::LuaError(msg)) if msg.contains("invalid argument")));
+    assert!(matches!(value, Err(Error::ArgumentTypeMismatch(2, _))));
     assert_eq!(lua.top(), 0);
 }
 
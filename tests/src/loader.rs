@@ -0,0 +1,102 @@
+#![allow(unused)]
+use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+use ljr::{Error, prelude::*};
+
+#[test]
+fn test_preload_is_lazy() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let loads = Rc::new(Cell::new(0));
+    let counter = loads.clone();
+    lua.preload("counter", move |lua| {
+        counter.set(counter.get() + 1);
+        Ok(create_table!(lua, { "answer" => 42 }))
+    });
+    assert_eq!(loads.get(), 0);
+
+    assert_eq!(
+        lua.do_string::<i32>("return require('counter').answer"),
+        Ok(42)
+    );
+    assert_eq!(
+        lua.do_string::<bool>("return require('counter') == require('counter')"),
+        Ok(true)
+    );
+    assert_eq!(loads.get(), 1);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_preload_error() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.preload("broken", |_| Err::<(), _>(Error::Generic("broken".into())));
+    assert_eq!(
+        lua.exec("require('broken')"),
+        Err(Error::Generic("broken".into()))
+    );
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_add_searcher() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let mut files = HashMap::new();
+    files.insert("util", "return { double = function(n) return n * 2 end }");
+    files.insert(
+        "main",
+        "local util = require('util') return util.double(21)",
+    );
+    lua.add_searcher(move |name| files.get(name).map(|code| Source::from(*code)));
+
+    assert_eq!(lua.do_string::<i32>("return require('main')"), Ok(42));
+    assert!(lua.exec("require('missing')").is_err());
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_searcher_chunk_name() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.add_searcher(|name| match name {
+        "bad" => Some(Source::new("return +").name("assets/bad.lua")),
+        "where" => Some(Source::new("return debug.getinfo(1, 'S').source")),
+        _ => None,
+    });
+
+    assert_eq!(
+        lua.do_string::<String>("return require('where')"),
+        Ok("@where".into())
+    );
+    match lua.exec("require('bad')") {
+        Err(Error::InvalidSyntax(msg)) => assert!(msg.contains("assets/bad.lua")),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_searcher_runs_before_filesystem() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.add_searcher(|name| (name == "string").then(|| Source::new("return 1")));
+    lua.add_searcher(|name| (name == "virtual").then(|| Source::new("return 'second'")));
+    lua.add_searcher(|name| (name == "virtual").then(|| Source::new("return 'first'")));
+
+    assert_eq!(
+        lua.do_string::<String>("return require('virtual')"),
+        Ok("first".into())
+    );
+    assert_eq!(
+        lua.do_string::<bool>("return require('string') == string"),
+        Ok(true)
+    );
+    assert_eq!(lua.top(), 0);
+}
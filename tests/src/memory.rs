@@ -0,0 +1,82 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+#[test]
+fn test_memory_tracking() {
+    let mut lua = Lua::builder().build();
+    lua.open_libs();
+
+    let before = lua.used_memory();
+    assert!(before > 0);
+
+    lua.exec("data = {} for i = 1, 10000 do data[i] = tostring(i) end")
+        .unwrap();
+    let after = lua.used_memory();
+    assert!(after > before);
+    assert!(lua.peak_memory().unwrap() >= after);
+
+    lua.exec("data = nil collectgarbage() collectgarbage()")
+        .unwrap();
+    assert!(lua.used_memory() < after);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_memory_limit_exceeded() {
+    let mut lua = Lua::builder().memory_limit(1024 * 1024).build();
+    lua.open_libs();
+
+    let result = lua.exec("local t = {} for i = 1, 1e8 do t[i] = i end");
+    assert_eq!(result, Err(Error::MemoryLimitExceeded));
+    assert!(lua.peak_memory().unwrap() <= 1024 * 1024);
+
+    let result = lua.exec("local s = 'x' while true do s = s .. s end");
+    assert_eq!(result, Err(Error::MemoryLimitExceeded));
+
+    assert_eq!(lua.do_string::<i32>("collectgarbage() return 1 + 1"), Ok(2));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_memory_limit_in_coroutine() {
+    let mut lua = Lua::builder().memory_limit(1024 * 1024).build();
+    lua.open_libs();
+
+    let func = lua
+        .load("local t = {} for i = 1, 1e8 do t[i] = {} end")
+        .into_function()
+        .unwrap();
+    let co = lua.create_thread(&func);
+    assert_eq!(co.resume::<_, ()>(()), Err(Error::MemoryLimitExceeded));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_memory_default_state() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    assert!(lua.used_memory() > 0);
+    assert_eq!(lua.peak_memory(), None);
+}
+
+#[test]
+fn test_memory_limit_with_rust_callbacks() {
+    let mut lua = Lua::builder().memory_limit(1024 * 1024).build();
+    lua.open_libs();
+
+    let make = lua.create_function(|lua, n: i32| {
+        let mut t = lua.create_table();
+        t.with_mut(|t| {
+            for i in 1..=n {
+                t.push(i);
+            }
+        });
+        Ok(t)
+    });
+    lua.with_globals_mut(|g| g.set("make", make));
+
+    let result = lua.exec("local all = {} while true do all[#all + 1] = make(100) end");
+    assert_eq!(result, Err(Error::MemoryLimitExceeded));
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,220 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Vec2 {
+    x: f64,
+    y: f64,
+}
+
+#[user_data]
+impl Vec2 {
+    fn new(x: f64, y: f64) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    #[meta(add)]
+    fn add(&self, other: &Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+
+    #[meta(sub)]
+    fn sub(&self, other: &Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+
+    #[meta(mul)]
+    fn scale(&self, factor: f64) -> Vec2 {
+        Vec2::new(self.x * factor, self.y * factor)
+    }
+
+    #[meta(div)]
+    fn div(&self, factor: f64) -> Vec2 {
+        Vec2::new(self.x / factor, self.y / factor)
+    }
+
+    #[meta(unm)]
+    fn neg(&self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+
+    #[meta(eq)]
+    fn eq(&self, other: &Vec2) -> bool {
+        self == other
+    }
+
+    #[meta(lt)]
+    fn lt(&self, other: &Vec2) -> bool {
+        self.len() < other.len()
+    }
+
+    #[meta(le)]
+    fn le(&self, other: &Vec2) -> bool {
+        self.len() <= other.len()
+    }
+
+    #[meta(len)]
+    fn len(&self) -> f64 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+
+    #[meta(tostring)]
+    fn to_string(&self) -> String {
+        format!("({}, {})", self.x, self.y)
+    }
+
+    #[meta(concat)]
+    fn concat(&self, suffix: &str) -> String {
+        format!("({}, {}){}", self.x, self.y, suffix)
+    }
+
+    #[meta(call)]
+    fn call(&self, x: f64, y: f64) -> f64 {
+        self.x * x + self.y * y
+    }
+}
+
+#[cfg(test)]
+fn vec2_lua() -> Lua {
+    let mut lua = Lua::new();
+    lua.open_libs();
+    lua.register("vec2", Vec2::new(0.0, 0.0));
+    lua.exec("vec2 = require 'vec2'").unwrap();
+    lua
+}
+
+#[test]
+fn test_meta_arith() {
+    let mut lua = vec2_lua();
+
+    let result = lua.do_string::<(f64, f64)>(
+        r#"
+        local v = vec2.new(1, 2) + vec2.new(3, 4) - vec2.new(1, 1)
+        v = -(v * 2)
+        return v:x(), v:y()
+        "#,
+    );
+    assert_eq!(result, Ok((-6.0, -10.0)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_meta_compare() {
+    let mut lua = vec2_lua();
+
+    let result = lua.do_string::<(bool, bool, bool, bool)>(
+        r#"
+        local a, b = vec2.new(1, 2), vec2.new(3, 4)
+        return a == vec2.new(1, 2), a ~= b, a < b, b <= a
+        "#,
+    );
+    assert_eq!(result, Ok((true, true, true, false)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_meta_len_tostring_concat_call() {
+    let mut lua = vec2_lua();
+
+    let result = lua.do_string::<(f64, String, String, f64)>(
+        r#"
+        local v = vec2.new(3, 4)
+        return #v, tostring(v), v .. '!', v(2, 1)
+        "#,
+    );
+    assert_eq!(result, Ok((5.0, "(3, 4)".into(), "(3, 4)!".into(), 10.0)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_meta_not_in_index() {
+    let mut lua = vec2_lua();
+
+    let result = lua
+        .do_string::<(bool, bool)>("local v = vec2.new(1, 1) return v.add == nil, v.__add == nil");
+    assert_eq!(result, Ok((true, true)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_meta_wrong_operand() {
+    let mut lua = vec2_lua();
+
+    let result = lua.do_string::<()>("local v = vec2.new(1, 1) + 1");
+    assert!(result.is_err());
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_meta_userdata_on_right() {
+    let mut lua = vec2_lua();
+
+    let result = lua.do_string::<(f64, f64)>(
+        r#"
+        local v = 2 * vec2.new(1, 2)
+        return v:x(), v:y()
+        "#,
+    );
+    assert_eq!(result, Ok((2.0, 4.0)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_meta_ordered_userdata_on_right() {
+    let mut lua = vec2_lua();
+
+    let result = lua.do_string::<f64>("return (vec2.new(2, 4) / 2):y()");
+    assert_eq!(result, Ok(2.0));
+
+    for code in [
+        "return 's' .. vec2.new(1, 2)",
+        "return 10 - vec2.new(1, 2)",
+        "return 10 / vec2.new(1, 2)",
+    ] {
+        let result = lua.do_string::<()>(code);
+        assert!(
+            matches!(result, Err(Error::ArgumentTypeMismatch(1, _))),
+            "{}: {:?}",
+            code,
+            result
+        );
+        assert_eq!(lua.top(), 0);
+    }
+}
+
+struct Plain;
+
+static PLAIN_REGS: [ljr::SyncLuaReg; 1] = [ljr::SyncLuaReg(ljr::sys::luaL_Reg {
+    name: std::ptr::null(),
+    func: ljr::dummy_trampoline,
+})];
+
+impl UserData for Plain {
+    fn name() -> *const i8 {
+        c"tests_Plain".as_ptr()
+    }
+
+    fn functions() -> &'static [ljr::sys::luaL_Reg] {
+        unsafe { &*(&PLAIN_REGS as *const [ljr::SyncLuaReg; 1] as *const [ljr::sys::luaL_Reg; 1]) }
+    }
+}
+
+#[test]
+fn test_meta_hand_written_user_data() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+    lua.register_type::<Plain>("Plain");
+    lua.with_globals_mut(|g| g.set("plain", Plain));
+
+    let result = lua.do_string::<(String, bool)>("return type(plain), next(Plain) == nil");
+    assert_eq!(result, Ok(("userdata".into(), true)));
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,183 @@
+#[cfg(test)]
+use ljr::{Error, prelude::*};
+
+#[test]
+fn test_integer_round_trip() {
+    let mut lua = Lua::new();
+    lua.with_globals_mut(|g| {
+        g.set("a", i8::MIN);
+        g.set("b", u8::MAX);
+        g.set("c", i16::MIN);
+        g.set("d", u16::MAX);
+        g.set("e", i32::MIN);
+        g.set("f", u32::MAX);
+        g.set("g", -(1i64 << 53));
+        g.set("h", 1u64 << 53);
+        g.set("i", -1isize);
+        g.set("j", 42usize);
+    });
+
+    lua.with_globals(|g| {
+        assert_eq!(g.get::<_, i8>("a"), Some(i8::MIN));
+        assert_eq!(g.get::<_, u8>("b"), Some(u8::MAX));
+        assert_eq!(g.get::<_, i16>("c"), Some(i16::MIN));
+        assert_eq!(g.get::<_, u16>("d"), Some(u16::MAX));
+        assert_eq!(g.get::<_, i32>("e"), Some(i32::MIN));
+        assert_eq!(g.get::<_, u32>("f"), Some(u32::MAX));
+        assert_eq!(g.get::<_, i64>("g"), Some(-(1i64 << 53)));
+        assert_eq!(g.get::<_, u64>("h"), Some(1u64 << 53));
+        assert_eq!(g.get::<_, isize>("i"), Some(-1));
+        assert_eq!(g.get::<_, usize>("j"), Some(42));
+    });
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_integer_checked_conversion() {
+    let mut lua = Lua::new();
+    let f = lua
+        .do_string::<FnRef>("return function(n) return n end")
+        .unwrap();
+
+    let value = f.call::<_, i32>(3.5);
+    assert!(matches!(
+        value,
+        Err(Error::InvalidNumberConversion(_, "i32"))
+    ));
+
+    let value = f.call::<_, u8>(300);
+    assert!(matches!(
+        value,
+        Err(Error::InvalidNumberConversion(_, "u8"))
+    ));
+
+    let value = f.call::<_, u32>(-1);
+    assert!(matches!(
+        value,
+        Err(Error::InvalidNumberConversion(_, "u32"))
+    ));
+
+    let value = f.call::<_, i64>(f64::INFINITY);
+    assert!(matches!(
+        value,
+        Err(Error::InvalidNumberConversion(_, "i64"))
+    ));
+
+    let value = lua.do_string::<u8>("return 3.5");
+    assert_eq!(value, Err(Error::WrongReturnType));
+
+    let value = f.call::<_, u8>(255);
+    assert_eq!(value, Ok(255));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_integer_push_without_loss() {
+    let lua = Lua::new();
+    let f = lua.create_function(|_, n: i32| Ok(n));
+
+    assert!(f.call::<_, i64>((1i64 << 53) + 1).is_err());
+    assert!(f.call::<_, u64>(u64::MAX).is_err());
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_boxed_int64_round_trip() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.with_globals_mut(|g| {
+        g.set("a", BoxedI64(i64::MIN));
+        g.set("b", BoxedU64(u64::MAX));
+        g.set("c", BoxedI64((1i64 << 53) + 1));
+    });
+
+    lua.with_globals(|g| {
+        assert_eq!(g.get::<_, BoxedI64>("a"), Some(BoxedI64(i64::MIN)));
+        assert_eq!(g.get::<_, BoxedU64>("b"), Some(BoxedU64(u64::MAX)));
+        assert_eq!(g.get::<_, BoxedI64>("c"), Some(BoxedI64((1i64 << 53) + 1)));
+    });
+
+    let value = lua.do_string::<bool>(
+        "return type(a) == 'cdata' and tostring(b) == '18446744073709551615ULL'",
+    );
+    assert_eq!(value, Ok(true));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_boxed_int64_arithmetic() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.with_globals_mut(|g| g.set("a", BoxedI64(1i64 << 60)));
+    let value = lua.do_string::<BoxedI64>("return a + 1");
+    assert_eq!(value, Ok(BoxedI64((1i64 << 60) + 1)));
+
+    let value = lua.do_string::<BoxedU64>("return 0xffffffffffffffffULL - 1");
+    assert_eq!(value, Ok(BoxedU64(u64::MAX - 1)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_boxed_int64_conversion() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let value = lua.do_string::<BoxedI64>("return 42");
+    assert_eq!(value, Ok(BoxedI64(42)));
+
+    let f = lua
+        .do_string::<FnRef>("return function(n) return n end")
+        .unwrap();
+
+    let value = f.call::<_, BoxedU64>(BoxedI64(-1));
+    assert!(matches!(
+        value,
+        Err(Error::InvalidNumberConversion(_, "u64"))
+    ));
+
+    let value = f.call::<_, BoxedI64>(BoxedU64(u64::MAX));
+    assert!(matches!(
+        value,
+        Err(Error::InvalidNumberConversion(_, "i64"))
+    ));
+
+    let value = f.call::<_, BoxedI64>("x");
+    assert!(matches!(value, Err(Error::UnexpectedType)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_callback_returning_lossy_integer() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    struct Big;
+
+    #[user_data]
+    impl Big {
+        fn pair() -> (i32, u64) {
+            (1, u64::MAX)
+        }
+    }
+
+    lua.register("big", Big);
+    let f = lua.create_function(|_, ()| Ok(u64::MAX));
+    lua.with_globals_mut(|g| g.set("big_fn", &f));
+
+    let result = lua.exec("big_fn()");
+    assert!(matches!(
+        result,
+        Err(Error::InvalidNumberConversion(_, "lua number"))
+    ));
+
+    let result = lua.do_string::<bool>(
+        r#"
+        local ok = pcall(require('big').pair)
+        return ok
+        "#,
+    );
+    assert_eq!(result, Ok(false));
+    assert_eq!(lua.top(), 0);
+}
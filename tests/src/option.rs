@@ -348,3 +348,128 @@ fn test_opt_slice_arg() {
     assert!(matches!(result, Ok(true)));
     assert_eq!(lua.top(), 0);
 }
+
+#[test]
+fn test_omitted_trailing_opt_args() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    struct Other {
+        value: i32,
+    }
+
+    #[user_data]
+    impl Other {
+        fn new(value: i32) -> Other {
+            Other { value }
+        }
+    }
+
+    struct Test;
+
+    #[user_data]
+    impl Test {
+        fn describe(
+            &self,
+            base: i32,
+            extra: Option<i32>,
+            name: Option<&str>,
+            other: Option<&Other>,
+        ) -> String {
+            format!(
+                "{} {:?} {:?} {:?}",
+                base,
+                extra,
+                name,
+                other.map(|o| o.value)
+            )
+        }
+
+        fn greet(name: Option<String>) -> String {
+            name.unwrap_or_else(|| "nobody".to_string())
+        }
+    }
+
+    lua.register("test", Test);
+    lua.register("other", Other { value: 0 });
+
+    let result = lua.do_string::<(String, String, String, String)>(
+        r#"
+        local test = require 'test'
+        local Other = require 'other'
+        return test:describe(1),
+            test:describe(1, 2),
+            test:describe(1, nil, 'x'),
+            test:describe(1, 2, 'x', Other.new(3))
+        "#,
+    );
+    assert_eq!(
+        result,
+        Ok((
+            "1 None None None".to_string(),
+            "1 Some(2) None None".to_string(),
+            "1 None Some(\"x\") None".to_string(),
+            "1 Some(2) Some(\"x\") Some(3)".to_string(),
+        ))
+    );
+    assert_eq!(lua.top(), 0);
+
+    let result = lua.do_string::<String>("return require('test').greet()");
+    assert_eq!(result, Ok("nobody".to_string()));
+
+    let result = lua.exec("require('test'):describe()");
+    assert_eq!(result, Err(ljr::Error::ArgumentCountMismatch(2, 1)));
+
+    let result = lua.exec("require('test'):describe(1, 2, 'x', nil, 5)");
+    assert_eq!(result, Err(ljr::Error::ArgumentCountMismatch(5, 6)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_nil_opt_ref_arg_before_value() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    struct Other {
+        value: i32,
+    }
+
+    #[user_data]
+    impl Other {
+        fn new(value: i32) -> Other {
+            Other { value }
+        }
+    }
+
+    struct Test;
+
+    #[user_data]
+    impl Test {
+        fn pair(&self, a: Option<&str>, b: Option<&str>) -> String {
+            format!("{:?} {:?}", a, b)
+        }
+
+        fn pick(&self, a: Option<&Other>, b: Option<&[u8]>, c: Option<&Other>) -> String {
+            format!("{:?} {:?} {:?}", a.map(|o| o.value), b, c.map(|o| o.value))
+        }
+    }
+
+    lua.register("test", Test);
+    lua.register("other", Other { value: 0 });
+
+    let result = lua.do_string::<(String, String)>(
+        r#"
+        local test = require 'test'
+        local Other = require 'other'
+        return test:pair(nil, 'x'), test:pick(nil, nil, Other.new(3))
+        "#,
+    );
+    assert_eq!(
+        result,
+        Ok((
+            "None Some(\"x\")".to_string(),
+            "None None Some(3)".to_string(),
+        ))
+    );
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,120 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+struct Player {
+    name: String,
+    hp: i32,
+    level: i32,
+}
+
+#[user_data]
+impl Player {
+    #[get]
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[get]
+    fn get_hp(&self) -> i32 {
+        self.hp
+    }
+
+    #[set]
+    fn set_hp(&mut self, value: i32) {
+        self.hp = value.max(0);
+    }
+
+    #[get(lvl)]
+    fn level(&self) -> i32 {
+        self.level
+    }
+
+    #[set(lvl)]
+    fn change_level(&mut self, value: i32) {
+        self.level = value;
+    }
+
+    fn heal(&mut self, amount: i32) {
+        self.hp += amount;
+    }
+}
+
+#[cfg(test)]
+fn player_lua() -> Lua {
+    let mut lua = Lua::new();
+    lua.open_libs();
+    lua.with_globals_mut(|g| {
+        g.set(
+            "player",
+            Player {
+                name: "soreto".into(),
+                hp: 10,
+                level: 1,
+            },
+        )
+    });
+    lua
+}
+
+#[test]
+fn test_property_get() {
+    let mut lua = player_lua();
+
+    let result = lua.do_string::<(String, i32, i32)>("return player.name, player.hp, player.lvl");
+    assert_eq!(result, Ok(("soreto".into(), 10, 1)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_property_set() {
+    let mut lua = player_lua();
+
+    let result = lua.do_string::<(i32, i32)>(
+        r#"
+        player.hp = -5
+        player.lvl = player.lvl + 1
+        return player.hp, player.lvl
+        "#,
+    );
+    assert_eq!(result, Ok((0, 2)));
+
+    lua.with_globals(|g| {
+        g.view("player", |p: &StackUd<Player>| {
+            assert_eq!(p.as_ref().hp, 0);
+            assert_eq!(p.as_ref().level, 2);
+        })
+    });
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_property_method_fallback() {
+    let mut lua = player_lua();
+
+    let result = lua.do_string::<(i32, bool, bool)>(
+        "player:heal(5) return player.hp, player.missing == nil, player.set_hp == nil",
+    );
+    assert_eq!(result, Ok((15, true, true)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_property_read_only() {
+    let mut lua = player_lua();
+
+    let result = lua.exec("player.name = 'other'");
+    assert_eq!(result, Err(Error::PropertyNotWritable("name".into())));
+
+    let result = lua.exec("player.missing = 1");
+    assert_eq!(result, Err(Error::PropertyNotWritable("missing".into())));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_property_set_wrong_type() {
+    let mut lua = player_lua();
+
+    let result = lua.exec("player.hp = 'full'");
+    assert!(matches!(result, Err(Error::ArgumentTypeMismatch(_, ref ty)) if ty.contains("i32")));
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,100 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+#[test]
+fn test_sandbox_blocks_unsafe_globals() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let sandbox = lua.create_sandbox();
+    let result = lua
+        .load(
+            r#"
+            return io == nil and debug == nil and load == nil and loadstring == nil
+                and require == nil and ffi == nil and os.execute == nil
+                and os.remove == nil and os.time ~= nil
+            "#,
+        )
+        .env(sandbox)
+        .eval::<bool>();
+    assert_eq!(result, Ok(true));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_sandbox_libraries_are_copies() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let sandbox = lua.create_sandbox();
+    let result = lua
+        .load("string.upper = nil x = string.format('%d', 7) return x")
+        .env(sandbox.clone())
+        .eval::<String>();
+    assert_eq!(result, Ok("7".into()));
+    assert_eq!(sandbox.with(|t| t.get::<_, String>("x")), Some("7".into()));
+
+    let result = lua
+        .load("return string.upper('a'), x == nil")
+        .eval::<(String, bool)>();
+    assert_eq!(result, Ok(("A".into(), true)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_sandbox_cannot_patch_host_string_lib() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let sandbox = lua.create_sandbox();
+    let result = lua
+        .load(
+            r#"
+            local mt = getmetatable("")
+            if mt then rawset(mt.__index, "upper", function() return "pwned" end) end
+            local t = setmetatable({}, { __index = { x = 1 } })
+            return mt == nil, getmetatable(t).__index.x, string.dump == nil
+            "#,
+        )
+        .env(sandbox)
+        .eval::<(bool, i32, bool)>();
+    assert_eq!(result, Ok((true, 1, true)));
+
+    let result = lua.load("return ('a'):upper()").eval::<String>();
+    assert_eq!(result, Ok("A".into()));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_sandbox_custom_allowlist() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let sandbox = lua.create_sandbox_with(&["type", "math.floor", "missing.field"]);
+    let result = lua
+        .load("return type(print), math.floor(1.5), math.ceil == nil, missing == nil")
+        .env(sandbox)
+        .eval::<(String, i32, bool, bool)>();
+    assert_eq!(result, Ok(("nil".into(), 1, true, true)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_func_set_get_env() {
+    let lua = Lua::new();
+    lua.open_libs();
+
+    let func = lua.load("return value").into_function().unwrap();
+    assert_eq!(func.call::<_, Option<i32>>(()), Ok(None));
+
+    let mut env = lua.create_table();
+    env.with_mut(|t| t.set("value", 5));
+    func.set_env(&env);
+    assert_eq!(func.call::<_, i32>(()), Ok(5));
+    assert!(func.get_env() == env);
+
+    let globals = lua.globals();
+    func.set_env(&globals);
+    assert!(func.get_env() == globals);
+    assert_eq!(lua.top(), 0);
+}
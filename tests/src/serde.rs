@@ -0,0 +1,180 @@
+#![allow(unused)]
+use std::collections::HashMap;
+
+use ::serde::{Deserialize, Serialize};
+use ljr::{
+    Error,
+    prelude::*,
+    serde::{
+        NoneRepr, Null, Options, SparseArrays, from_value, from_value_with, to_value, to_value_with,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Mode {
+    Coop,
+    Versus { teams: u8 },
+    Custom(String, i32),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ServerConfig {
+    name: String,
+    port: u16,
+    tags: Vec<String>,
+    motd: Option<String>,
+    limits: HashMap<String, i32>,
+    modes: Vec<Mode>,
+    #[serde(default)]
+    debug: bool,
+}
+
+#[cfg(test)]
+fn config() -> ServerConfig {
+    ServerConfig {
+        name: "main".into(),
+        port: 7777,
+        tags: vec!["eu".into(), "ranked".into()],
+        motd: None,
+        limits: HashMap::from([("players".into(), 16)]),
+        modes: vec![
+            Mode::Coop,
+            Mode::Versus { teams: 2 },
+            Mode::Custom("ctf".into(), 3),
+        ],
+        debug: true,
+    }
+}
+
+#[test]
+fn test_serde_to_value() {
+    let mut lua = Lua::new();
+    let value = to_value(&lua, &config()).unwrap();
+    lua.with_globals_mut(|g| g.set("config", value));
+
+    let result = lua.do_string::<(String, i32, String, bool)>(
+        "return config.name, config.port, config.tags[2], config.motd == nil",
+    );
+    assert_eq!(result, Ok(("main".into(), 7777, "ranked".into(), true)));
+
+    let result = lua.do_string::<(i32, String, i32, String)>(
+        "return config.limits.players, config.modes[1], config.modes[2].Versus.teams, config.modes[3].Custom[1]",
+    );
+    assert_eq!(result, Ok((16, "Coop".into(), 2, "ctf".into())));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_serde_round_trip() {
+    let lua = Lua::new();
+    let value = to_value(&lua, &config()).unwrap();
+    assert_eq!(from_value::<ServerConfig, _>(&value), Ok(config()));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_serde_from_script() {
+    let mut lua = Lua::new();
+    lua.exec(
+        r#"
+        config = {
+            name = "script",
+            port = 80,
+            tags = {},
+            limits = { rooms = 4 },
+            modes = { "Coop", { Versus = { teams = 3 } } },
+        }
+        "#,
+    )
+    .unwrap();
+
+    let result =
+        lua.with_globals(|g| g.view("config", |v: &StackValue| from_value::<ServerConfig, _>(v)));
+    let expected = ServerConfig {
+        name: "script".into(),
+        port: 80,
+        tags: vec![],
+        motd: None,
+        limits: HashMap::from([("rooms".into(), 4)]),
+        modes: vec![Mode::Coop, Mode::Versus { teams: 3 }],
+        debug: false,
+    };
+    assert_eq!(result, Some(Ok(expected)));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_serde_none_as_null() {
+    let mut lua = Lua::new();
+    let options = Options::default().none(NoneRepr::Null);
+    let value = to_value_with(&lua, &vec![Some(1), None, Some(3)], options).unwrap();
+    lua.with_globals_mut(|g| {
+        g.set("list", value);
+        g.set("null", Null);
+    });
+
+    let result = lua.do_string::<(i32, bool)>("return #list, list[2] == null");
+    assert_eq!(result, Ok((3, true)));
+
+    let value = lua
+        .with_globals(|g| g.view("list", |v: &StackValue| v.to_owned()))
+        .unwrap();
+    assert_eq!(
+        from_value::<Vec<Option<i32>>, _>(&value),
+        Ok(vec![Some(1), None, Some(3)])
+    );
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_serde_sparse_arrays() {
+    let mut lua = Lua::new();
+    lua.exec("list = { [1] = 1, [3] = 3 }").unwrap();
+    let value = lua
+        .with_globals(|g| g.view("list", |v: &StackValue| v.to_owned()))
+        .unwrap();
+
+    let result = from_value::<Vec<Option<i32>>, _>(&value);
+    assert_eq!(result, Ok(vec![Some(1), None, Some(3)]));
+
+    let options = Options::default().sparse_arrays(SparseArrays::Reject);
+    let result = from_value_with::<Vec<Option<i32>>, _>(&value, options);
+    assert!(matches!(result, Err(Error::DeserializeError(_))));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_serde_sparse_arrays_too_sparse() {
+    let mut lua = Lua::new();
+    lua.exec("list = { [1e9] = 1 } other = { [1] = 1, [5] = 5 }")
+        .unwrap();
+    let (list, other) = lua
+        .with_globals(|g| {
+            let list = g.view("list", |v: &StackValue| v.to_owned())?;
+            let other = g.view("other", |v: &StackValue| v.to_owned())?;
+            Some((list, other))
+        })
+        .unwrap();
+
+    let result = from_value::<Vec<Option<i32>>, _>(&list);
+    assert!(matches!(result, Err(Error::DeserializeError(_))));
+    let result = from_value::<Vec<Option<i32>>, _>(&other);
+    assert!(matches!(result, Err(Error::DeserializeError(_))));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_serde_type_errors() {
+    let lua = Lua::new();
+    let value = to_value(&lua, &"text").unwrap();
+    let result = from_value::<ServerConfig, _>(&value);
+    assert!(matches!(result, Err(Error::DeserializeError(ref msg)) if msg.contains("string")));
+
+    let value = to_value(&lua, &HashMap::from([("port", "x")])).unwrap();
+    let result = from_value::<HashMap<String, u16>, _>(&value);
+    assert!(matches!(result, Err(Error::DeserializeError(_))));
+
+    let result = to_value(&lua, &u64::MAX);
+    assert!(result.is_err());
+    assert_eq!(lua.top(), 0);
+}
@@ -131,7 +131,7 @@ fn test_u8_slice_arg_wrong_type() {
     );
 
     assert!(
-        matches!(result, Err(Error::LuaError(msg)) if msg.contains("invalid argument") || msg.contains("bad argument"))
+        matches!(result, Err(Error::ArgumentTypeMismatch(..)))
     );
     assert_eq!(lua.top(), 0);
 }
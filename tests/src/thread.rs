@@ -0,0 +1,141 @@
+#[cfg(test)]
+use ljr::{Error, prelude::*, value::Kind};
+
+#[test]
+fn test_thread_resume_yield() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let func = lua
+        .do_string::<FnRef>(
+            r#"
+            return function(a, b)
+                local c = coroutine.yield(a + b)
+                local d, e = coroutine.yield(c * 2)
+                return d + e
+            end
+            "#,
+        )
+        .unwrap();
+
+    let co = lua.create_thread(&func);
+    assert_eq!(co.status(), ThreadStatus::Suspended);
+
+    assert_eq!(co.resume::<_, i32>((1, 2)), Ok(3));
+    assert_eq!(co.status(), ThreadStatus::Suspended);
+
+    assert_eq!(co.resume::<_, i32>(10), Ok(20));
+    assert_eq!(co.resume::<_, i32>((4, 5)), Ok(9));
+    assert_eq!(co.status(), ThreadStatus::Dead);
+
+    assert_eq!(co.resume::<_, ()>(()), Err(Error::CoroutineNotResumable));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_thread_from_lua() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let co = lua
+        .do_string::<ThreadRef>(
+            r#"
+            return coroutine.create(function()
+                for i = 1, 3 do
+                    coroutine.yield(i, tostring(i))
+                end
+            end)
+            "#,
+        )
+        .unwrap();
+
+    for i in 1..=3 {
+        let (n, s) = co.resume::<_, (i32, String)>(()).unwrap();
+        assert_eq!(n, i);
+        assert_eq!(s, i.to_string());
+    }
+
+    assert_eq!(co.resume::<_, Option<i32>>(()), Ok(None));
+    assert_eq!(co.status(), ThreadStatus::Dead);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_thread_error() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let func = lua
+        .do_string::<FnRef>("return function() coroutine.yield(1); error('oops') end")
+        .unwrap();
+    let co = lua.create_thread(&func);
+
+    assert_eq!(co.resume::<_, i32>(()), Ok(1));
+    let result = co.resume::<_, ()>(());
+    assert!(matches!(result, Err(Error::LuaError(ref msg)) if msg.contains("oops")));
+    assert_eq!(co.status(), ThreadStatus::Dead);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_thread_status_inside_coroutine() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let status = lua.create_function(|_, co: ThreadRef| Ok(format!("{:?}", co.status())));
+    lua.with_globals_mut(|g| g.set("status_of", status));
+
+    let result = lua.do_string::<(String, String)>(
+        r#"
+        local outer
+        local inner = coroutine.create(function()
+            return status_of(outer)
+        end)
+        outer = coroutine.create(function()
+            local _, s = coroutine.resume(inner)
+            return s
+        end)
+        local _, s = coroutine.resume(outer)
+        return s, status_of(inner)
+        "#,
+    );
+    assert_eq!(result, Ok(("Normal".to_string(), "Dead".to_string())));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_thread_values_outlive_coroutine() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let func = lua
+        .do_string::<FnRef>("return function() coroutine.yield({ value = 7 }) end")
+        .unwrap();
+    let co = lua.create_thread(&func);
+    let table = co.resume::<_, TableRef>(()).unwrap();
+    assert_eq!(co.resume::<_, ()>(()), Ok(()));
+    drop(co);
+
+    lua.do_string::<()>("collectgarbage()").unwrap();
+    assert_eq!(table.with(|t| t.get::<_, i32>("value")), Some(7));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_thread_value_kind() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.exec("co = coroutine.create(function() end)").unwrap();
+    lua.with_globals(|g| {
+        g.view("co", |v: &StackValue| {
+            assert_eq!(v.kind(), Kind::Thread);
+
+            let co = v.as_thread();
+            assert_eq!(co.status(), ThreadStatus::Suspended);
+            assert!(v.with_thread(|t| t == &co));
+            assert_eq!(v.to_owned().kind(), Kind::Thread);
+        })
+    });
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,86 @@
+#![allow(unused)]
+use ljr::{Error, error::RuntimeError, prelude::*};
+
+#[test]
+fn test_traceback_frames() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let result = lua.exec(
+        r#"
+        local function inner()
+            error("boom")
+        end
+
+        function outer()
+            inner()
+        end
+
+        outer()
+        "#,
+    );
+    let Err(Error::LuaError(err)) = result else {
+        panic!("expected lua error, got {:?}", result);
+    };
+
+    assert!(err.message.ends_with("boom"));
+    let names: Vec<_> = err.traceback.iter().map(|f| f.name.as_deref()).collect();
+    assert_eq!(names[..3], [Some("error"), Some("inner"), Some("outer")]);
+    assert_eq!(err.traceback[0].source, "[C]");
+    assert_eq!(err.traceback[1].line, Some(3));
+    assert_eq!(err.traceback[2].line, Some(7));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_traceback_display() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let result = lua.exec("local function f() error('boom') end f()");
+    let msg = result.unwrap_err().to_string();
+    assert!(msg.contains("boom\nstack traceback:"));
+    assert!(msg.contains(":1: in function 'f'"));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_traceback_fn_call() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let func = lua
+        .do_string::<FnRef>("return function(v) if v then error('bad') end return 1 end")
+        .unwrap();
+    assert_eq!(func.call::<_, i32>(false), Ok(1));
+
+    let result = func.call::<_, i32>(true);
+    assert!(matches!(result, Err(Error::LuaError(ref e)) if e.traceback.len() >= 2));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_traceback_rust_callback() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let fail = lua.create_function(|_: &Lua, ()| -> Result<(), Error> {
+        Err(Error::LuaError(RuntimeError {
+            message: "from rust".into(),
+            traceback: vec![],
+        }))
+    });
+    lua.with_globals_mut(|g| g.set("fail", fail));
+
+    let result = lua.exec("local function caller() fail() end caller()");
+    let Err(Error::LuaError(err)) = result else {
+        panic!("expected lua error");
+    };
+    assert!(err.contains("from rust"));
+    assert!(
+        err.traceback
+            .iter()
+            .any(|f| f.name.as_deref() == Some("caller"))
+    );
+    assert_eq!(lua.top(), 0);
+}
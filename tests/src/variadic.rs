@@ -0,0 +1,138 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*, value::Kind};
+
+struct Logger {
+    lines: Vec<String>,
+}
+
+#[user_data]
+impl Logger {
+    fn new() -> Self {
+        Self { lines: vec![] }
+    }
+
+    fn log(&mut self, level: i32, parts: Variadic<String>) -> usize {
+        self.lines.push(format!("{level}: {}", parts.join(" ")));
+        parts.len()
+    }
+
+    fn lines(&self) -> Variadic<String> {
+        self.lines.iter().cloned().collect()
+    }
+}
+
+#[test]
+fn test_variadic_user_data() {
+    let mut lua = Lua::new();
+    lua.register_type::<Logger>("Logger");
+
+    let result = lua.do_string::<(i32, i32, String, String)>(
+        r#"
+        local logger = Logger.new()
+        local a = logger:log(1, "hello", "world")
+        local b = logger:log(2)
+        return a, b, logger:lines()
+        "#,
+    );
+    assert_eq!(result, Ok((2, 0, "1: hello world".into(), "2: ".into())));
+
+    let result = lua.exec("Logger.new():log()");
+    assert_eq!(result, Err(Error::ArgumentCountMismatch(2, 1)));
+    let result = lua.exec("Logger.new():log(1, 2)");
+    assert!(matches!(result, Err(Error::ArgumentTypeMismatch(_, _))));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_variadic_function_args() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let sum = lua.create_function(|_, (first, rest): (i32, Variadic<i32>)| {
+        Ok(first + rest.iter().sum::<i32>())
+    });
+    let mut globals = lua.globals();
+    globals.with_mut(|g| g.set("sum", &sum));
+
+    assert_eq!(lua.do_string::<i32>("return sum(1)"), Ok(1));
+    assert_eq!(lua.do_string::<i32>("return sum(1, 2, 3, 4)"), Ok(10));
+    assert!(lua.exec("sum()").is_err());
+    assert_eq!(sum.call::<_, i32>((1, Variadic(vec![2, 3]))), Ok(6));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_variadic_call_results() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let func = lua
+        .do_string::<FnRef>("return function(...) return select('#', ...), ... end")
+        .unwrap();
+
+    let result = func.call::<_, Variadic<i32>>(Variadic(vec![5, 6, 7]));
+    assert_eq!(result, Ok(Variadic(vec![3, 5, 6, 7])));
+
+    let result = func.call::<_, (i32, Variadic<i32>)>(());
+    assert_eq!(result, Ok((0, Variadic(vec![]))));
+
+    let values = func.call::<_, MultiValue>(("a", true)).unwrap();
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[0].as_number(), 2.0);
+    assert_eq!(values[1].as_str().as_str(), "a");
+    assert_eq!(values[2].kind(), Kind::Bool);
+
+    let echoed = func.call::<_, MultiValue>(&values).unwrap();
+    assert_eq!(echoed.len(), 4);
+    assert_eq!(echoed[0].as_number(), 3.0);
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_variadic_do_string() {
+    let mut lua = Lua::new();
+
+    assert_eq!(
+        lua.do_string::<Variadic<String>>("return 'a', 'b', 'c'"),
+        Ok(Variadic(vec!["a".into(), "b".into(), "c".into()]))
+    );
+    assert_eq!(
+        lua.do_string::<Variadic<i32>>("return"),
+        Ok(Variadic(vec![]))
+    );
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_variadic_return_from_callback() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let range = lua.create_function(|_, n: i32| Ok((1..=n).collect::<Variadic<i32>>()));
+    let mut globals = lua.globals();
+    globals.with_mut(|g| g.set("range", &range));
+
+    assert_eq!(lua.do_string::<i32>("return select('#', range(5))"), Ok(5));
+    assert_eq!(lua.do_string::<i32>("return select('#', range(0))"), Ok(0));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_variadic_thread_resume() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let func = lua
+        .do_string::<FnRef>("return function(...) coroutine.yield(...) return 'done' end")
+        .unwrap();
+    let thread = lua.create_thread(&func);
+    assert_eq!(
+        thread.resume::<_, Variadic<i32>>(Variadic(vec![1, 2, 3])),
+        Ok(Variadic(vec![1, 2, 3]))
+    );
+    assert_eq!(
+        thread.resume::<_, Variadic<String>>(()),
+        Ok(Variadic(vec!["done".into()]))
+    );
+    assert_eq!(lua.top(), 0);
+}
@@ -0,0 +1,163 @@
+#![allow(unused)]
+use ljr::{Error, prelude::*};
+
+struct Handle {
+    value: i32,
+}
+
+#[user_data]
+impl Handle {
+    fn value(&self) -> i32 {
+        self.value
+    }
+}
+
+#[cfg(test)]
+fn collect(lua: &mut Lua) {
+    lua.exec("collectgarbage() collectgarbage()").unwrap();
+}
+
+#[test]
+fn test_weak_table_upgrade() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let table = lua.do_string::<TableRef>("return { x = 10 }").unwrap();
+    let weak = table.downgrade();
+    assert_eq!(weak.with(|t| t.get::<_, i32>("x")), Some(10));
+
+    let strong = weak.upgrade().unwrap();
+    assert_eq!(strong.with(|t| t.get::<_, i32>("x")), Some(10));
+
+    drop(table);
+    drop(strong);
+    collect(&mut lua);
+
+    assert!(weak.upgrade().is_none());
+    assert!(matches!(
+        weak.try_with(|t| t.get::<_, i32>("x")),
+        Err(Error::ValueCollected)
+    ));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_weak_table_kept_alive_by_lua() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let weak = lua
+        .do_string::<TableRef>("t = { 1, 2, 3 } return t")
+        .unwrap()
+        .downgrade();
+    collect(&mut lua);
+    assert_eq!(weak.with(|t| t.len()), 3);
+
+    lua.exec("t = nil").unwrap();
+    collect(&mut lua);
+    assert!(weak.upgrade().is_none());
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_weak_fn_call() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let func = lua
+        .do_string::<FnRef>("return function(a, b) return a + b end")
+        .unwrap();
+    let weak = func.downgrade();
+    assert_eq!(weak.call::<_, i32>((1, 2)), Ok(3));
+
+    drop(func);
+    collect(&mut lua);
+    assert!(weak.upgrade().is_none());
+    assert_eq!(weak.call::<_, i32>((1, 2)), Err(Error::ValueCollected));
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_weak_ud() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.with_globals_mut(|g| g.set("handle", Handle { value: 7 }));
+    let weak = lua
+        .do_string::<UdRef<Handle>>("return handle")
+        .unwrap()
+        .downgrade();
+    assert_eq!(weak.upgrade().map(|h| h.with(|h| h.value)), Some(7));
+
+    let strong = weak.upgrade().unwrap();
+    let guard = strong.as_ref();
+    lua.exec("handle = nil").unwrap();
+    collect(&mut lua);
+    assert_eq!(guard.value, 7);
+    drop(guard);
+    drop(strong);
+
+    collect(&mut lua);
+    assert!(weak.upgrade().is_none());
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_weak_value() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    lua.exec("v = {} n = nil").unwrap();
+    let value = lua
+        .with_globals(|g| g.view("v", |v: &StackValue| v.to_owned()))
+        .unwrap();
+    lua.exec("v = nil").unwrap();
+
+    let weak = value.downgrade();
+    assert!(weak.try_with_table(|t| t.len()).is_ok());
+
+    drop(value);
+    collect(&mut lua);
+    assert!(weak.upgrade().is_none());
+    assert!(matches!(
+        weak.try_with_table(|t| t.len()),
+        Err(Error::ValueCollected)
+    ));
+
+    let nil = lua
+        .with_globals(|g| g.view("n", |v: &StackValue| v.to_owned().downgrade()))
+        .unwrap();
+    assert!(nil.upgrade().is_some());
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_weak_str() {
+    let mut lua = Lua::new();
+
+    let s = lua.do_string::<StrRef>("return 'hello'").unwrap();
+    let weak = s.downgrade();
+    assert_eq!(weak.as_str(), "hello");
+    assert_eq!(
+        weak.upgrade().map(|s| s.as_str().to_string()),
+        Some("hello".into())
+    );
+    assert_eq!(lua.top(), 0);
+}
+
+#[test]
+fn test_weak_clone() {
+    let mut lua = Lua::new();
+    lua.open_libs();
+
+    let table = lua.do_string::<TableRef>("return { x = 1 }").unwrap();
+    let weak = table.downgrade();
+    let other = weak.clone();
+    drop(weak);
+    assert_eq!(other.with(|t| t.get::<_, i32>("x")), Some(1));
+
+    drop(table);
+    collect(&mut lua);
+    assert!(other.upgrade().is_none());
+    assert_eq!(lua.top(), 0);
+}